@@ -1,8 +1,13 @@
 use ratatui::prelude::*;
+use tui_textarea::{CursorMove, Input};
+use std::time::Duration;
 use crossterm::{
+    cursor::Show,
     event::{
         self,
+        DisableFocusChange,
         DisableMouseCapture,
+        EnableFocusChange,
         EnableMouseCapture
     }, execute, terminal::{
         disable_raw_mode,
@@ -13,107 +18,921 @@ use crossterm::{
 };
 
 pub mod file;
-use file::File;
+use file::{File, SaveError, Stamp};
+
+use crate::config::Settings;
 
 pub mod ui;
 
+pub mod editor;
+use editor::Viewport;
+
+pub mod markdown;
+use markdown::{highlight::Highlighter, preview::Preview, reflow, table};
+
 pub mod input;
 
-#[allow(dead_code)]
+pub mod finder;
+use finder::Finder;
+
+pub mod diff;
+use diff::DiffView;
+
+pub mod swap;
+
+pub mod timers;
+use timers::{Timer, Timers};
+
+pub mod keymap;
+use keymap::Keymap;
+
+pub mod vim;
+use vim::{Command, Operator, Pending, Register, Span, Target};
+
+#[cfg(test)]
+mod tests;
+
+// The loop wakes up at least this often, even without input or timers
+const TICK: Duration = Duration::from_millis(250);
+
+// How long a message stays under the note
+const INFO_FOR: Duration = Duration::from_secs(5);
+
 #[derive(Debug)]
 enum Message {
     Edit,
     Save,
     View,
+    Source,
+    Split,
+    Wrap,
+    Reflow,
+    Table(table::Edit),
+    Undo,
+    Redo,
+    Type(Input), // Goes to the textarea as is
+    Normal(char), // Key of a vim command in the source view
+    Abort, // Drops a half typed vim command
+    Move(CursorMove),
+    MoveRow(bool), // Down or up a row on screen, through wrapped lines
+    Scroll(Scroll), // Whatever is in front: preview, source, finder preview or diff
+    Find,
+    Select(isize), // Moves the finder selection
+    Query(Query),
+    Pick,
+    Confirm(Choice),
+    Focus(bool),
+    Info(String),
+    Autosave,
+    Check,
+    Swap,
+    Expire,
     Done
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scroll {
+    Rows(isize),
+    Pages(isize),
+    Top,
+    Bottom,
+}
+
+// Edits to the finder query
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Query {
+    Push(char),
+    Pop,
+    Clear,
+}
+
+// Questions asked in a popup while in Mode::Save
+#[derive(Debug)]
+enum Prompt {
+    // Leaving the note with unsaved changes
+    Unsaved(Message),
+    // The note changed on disk, with what to do once that's sorted out
+    Changed(Option<Message>),
+    // A swap file was left behind, with its lines
+    Recover(Vec<String>),
+}
+
+// Answers to the prompts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Choice {
+    Save,
+    Discard,
+    Reload,
+    Overwrite,
+    Recover,
+    Diff,
+    Cancel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Mode {
 	Edit, // ing...
-	Save, // ing...
+	Save, // ing... Asking what to do with unsaved or outside changes
 	View, // ing...
+	Visual, // Selecting chars in the source
+	VisualLine, // Selecting whole lines in the source
+	Find, // ing...
 	Exit, // ing...
 }
 
-#[allow(dead_code)]
 pub struct Model {
 	mode: Mode, // Default: View
 	file: File,
-	is_focused: bool // Feedback
+	is_focused: bool, // Feedback
+	highlighter: Highlighter,
+	viewport: Viewport,
+	preview: Preview,
+	raw: bool, // View mode shows the source instead of the preview
+	split: bool, // Source and preview side by side
+	wrap: bool, // Soft wrap long lines
+	settings: Settings,
+	keymap: Keymap, // Defaults with the config file's bindings on top
+	finder: Finder,
+	last_mode: Mode, // Mode to go back to when the finder closes
+	info: Option<String>, // Feedback shown under the note
+	prompt: Option<(Prompt, Mode)>, // Open prompt, with the mode to go back to
+	diff: Option<DiffView>, // Shown in the prompt when asked for
+	timers: Timers, // Disk checks, swap files, autosaves and messages going away
+	conflict: Option<Stamp>, // Version on disk already asked about
+	pending: Pending, // Vim command being typed
+	register: Register, // Text deleted or yanked last
+	last_change: Option<(Command, Vec<Input>)>, // Repeated with `.`, with the keys typed after `c`
+	typed: Option<Vec<Input>>, // Keys typed since `c`, the change ends with anything else
+	anchor: (usize, usize), // Where the visual selection started, the cursor is the other end
 }
 
 impl Model {
-    pub fn new(file :File) -> Model {
-    	Model {
+    pub fn new(file :File, settings: Settings) -> Model {
+    	let info = file.read_only.as_ref().map(|e| format!("Opened read-only: {e}"));
+
+    	let mut model = Model {
     		mode: Mode::View,
-    		file: file,
+    		file,
     		is_focused: true,
-    	}
+    		highlighter: Highlighter::default(),
+    		viewport: Viewport::default(),
+    		preview: Preview::default(),
+    		raw: false,
+    		split: false,
+    		wrap: settings.soft_wrap,
+    		// Mistakes were reported before opening, see main
+    		keymap: Keymap::new(&settings.keymap).unwrap_or_default(),
+    		settings,
+    		finder: Finder::default(),
+    		last_mode: Mode::View,
+    		info,
+    		prompt: None,
+    		diff: None,
+    		timers: Timers::default(),
+    		conflict: None,
+    		pending: Pending::default(),
+    		register: Register::default(),
+    		last_change: None,
+    		typed: None,
+    		anchor: (0, 0),
+    	};
+
+    	model.offer_recovery();
+    	model
     }
 
     // TODO:
     pub fn init(mut self) -> Result<(), Box<dyn std::error::Error>> {
         // Setup terminal
+        // From here on a panic or an error still leaves the terminal as it was
+        restore_on_panic();
+        let _restore = Restore;
+
         enable_raw_mode()?;
         let mut stdout = std::io::stdout();
 
         execute!(
             stdout,
             EnterAlternateScreen,
-            EnableMouseCapture
+            EnableMouseCapture,
+            EnableFocusChange
         )?;
 
         let backend = CrosstermBackend::new(stdout);
         let mut terminal = Terminal::new(backend)?;
 
+        // Timers that repeat for the whole session
+        self.update(Message::Check);
+        self.update(Message::Swap);
+        if self.info.is_some() { self.timers.set(Timer::Info, INFO_FOR); }
+
+        let mut redraw = true;
+
         // App loop
         loop {
             // Render
-            terminal.draw(|f| ui::render_frame(&mut self, f))?;
+            // Only when something changed since the last frame
+            if redraw { terminal.draw(|f| ui::render_frame(&mut self, f))?; }
+
+            let before = self.shown();
 
             // Input Events
-            input::read_input(event::read(), &mut self);
+            // Waits a tick at most, less if a timer goes off sooner
+            let wait = self.timers.next_in().map_or(TICK, |next| next.min(TICK));
+
+            redraw = event::poll(wait)?;
+
+            if redraw {
+                input::read_input(event::read(), &mut self);
+
+                if self.settings.autosave {
+                    self.timers.set(Timer::Autosave, Duration::from_secs(self.settings.autosave_idle.into()));
+                }
+            }
+
+            for timer in self.timers.due() {
+                self.update(match timer {
+                    Timer::Check => Message::Check,
+                    Timer::Swap => Message::Swap,
+                    Timer::Autosave => Message::Autosave,
+                    Timer::Info => Message::Expire,
+                });
+            }
+
+            // New messages go away after a while
+            if self.info.is_some() && self.info != before.1 {
+                self.timers.set(Timer::Info, INFO_FOR);
+            }
+
+            redraw |= self.shown() != before;
 
             // End app
             if self.mode == Mode::Exit { break; }
         }
 
-        // Restore terminal
-        disable_raw_mode()?;
-        execute!(
-            terminal.backend_mut(),
-            LeaveAlternateScreen,
-            DisableMouseCapture,
-        )?;
-        terminal.show_cursor()?;
-
         Ok(())
     }
 
     // TODO: Rework into multiple functions
     // Ex.: Model::to_edit()
-    #[allow(dead_code)]
     fn update(&mut self, msg: Message) {
+        let background = matches!(msg,
+            Message::Info(_) | Message::Autosave | Message::Check | Message::Swap | Message::Expire | Message::Focus(_)
+        );
+
+        if !background && !matches!(msg, Message::Type(_)) { self.end_change(); }
+
         match msg {
             Message::Edit => {
+                if !self.writable() { return; }
+
+                self.leave_preview();
                 self.mode = Mode::Edit;
-                //self.info = None;
+                self.info = None;
             },
 
             Message::Save => {
                 self.mode = Mode::View;
-                let _ = self.file.save();
-                //self.info = Some("File has been saved!".to_string());
+                self.save(None);
             },
 
             Message::View => {
                 self.mode = Mode::View;
+                self.file.textarea.cancel_selection();
+            },
+
+            Message::Source => {
+                self.leave_preview();
+                self.raw = !self.raw;
+            },
+
+            Message::Split => {
+                self.leave_preview();
+                self.split = !self.split;
+            },
+
+            Message::Wrap => {
+                self.wrap = !self.wrap;
+            },
+
+            // A selection is reflowed like `gq` does
+            Message::Reflow if self.selection().is_some() => {
+                self.command(Command::Apply(Operator::Reflow, Target::Selection, None));
+            },
+
+            Message::Reflow => {
+                if !self.writable() { return; }
+
+                let (row, _) = self.file.textarea.cursor();
+                let lines = self.file.textarea.lines();
+
+                if let Some(range) = reflow::paragraph_at(lines, row) {
+                    let width = self.settings.reflow_width as usize;
+                    let text = reflow::reflow(lines, range.clone(), width);
+
+                    self.file.replace_lines(range, text);
+                }
+            },
+
+            Message::Table(edit) => {
+                if !self.writable() { return; }
+
+                let (row, col) = self.file.textarea.cursor();
+                let lines = self.file.textarea.lines();
+
+                let Some(range) = table::table_at(lines, row) else { return };
+                let Some(mut parsed) = table::Table::parse(&lines[range.clone()]) else { return };
+
+                let cell = table::cell_at(&lines[row], col);
+                let (line, cell) = parsed.edit(edit, row - range.start, cell);
+
+                let mut rendered = parsed.render();
+                if line == rendered.len() { rendered.push(String::new()); }
+
+                let col = table::cell_start(&rendered[line], cell);
+                self.file.replace_lines(range.clone(), rendered);
+
+                let row = range.start + line;
+                self.file.textarea.move_cursor(CursorMove::Jump(row as u16, col as u16));
+            },
+
+            Message::Undo => {
+                if !self.writable() { return; }
+
+                self.file.undo();
+            },
+
+            Message::Redo => {
+                if !self.writable() { return; }
+
+                self.file.redo();
+            },
+
+            Message::Type(input) => {
+                if let Some(typed) = &mut self.typed { typed.push(input.clone()); }
+
+                let modified = self.file.textarea.input(input);
+                self.file.step(modified);
+            },
+
+            Message::Normal(c) => {
+                let visual = matches!(self.mode, Mode::Visual | Mode::VisualLine);
+                if let Some(command) = self.pending.push(c, visual) { self.command(command); }
+            },
+
+            Message::Abort => {
+                self.pending.clear();
+            },
+
+            Message::Move(movement) => {
+                self.file.textarea.move_cursor(movement);
+            },
+
+            Message::MoveRow(down) => {
+                self.viewport.move_cursor(&mut self.file.textarea, down);
+            },
+
+            Message::Scroll(scroll) => {
+                self.scroll(scroll);
+            },
+
+            Message::Find if self.mode == Mode::Find => {
+                self.mode = self.last_mode;
+            },
+
+            Message::Find => {
+                let Some(root) = self.settings.folder_path.clone() else { return };
+
+                match self.finder.open(root) {
+                    Ok(()) => {
+                        self.last_mode = self.mode;
+                        self.mode = Mode::Find;
+                    },
+
+                    Err(e) => self.info = Some(format!("Notes not listed: {e}")),
+                }
+            },
+
+            Message::Select(rows) => {
+                self.finder.select(rows);
+            },
+
+            Message::Query(edit) => match edit {
+                Query::Push(c) => self.finder.push(c),
+                Query::Pop => self.finder.pop(),
+                Query::Clear => self.finder.clear(),
+            },
+
+            // Both leave the note, unsaved changes are asked about first
+            msg @ (Message::Pick | Message::Done) => {
+                if self.file.is_dirty() {
+                    self.ask(Prompt::Unsaved(msg));
+                } else {
+                    self.leave(msg);
+                }
+            },
+
+            Message::Confirm(choice) => {
+                let Some((prompt, mode)) = self.prompt.take() else { return };
+                let showing_diff = self.diff.take().is_some();
+                self.mode = mode;
+
+                match (prompt, choice) {
+                    // Toggles the diff, the question stays
+                    (prompt, Choice::Diff) => {
+                        if !showing_diff {
+                            let disk = self.file.on_disk();
+
+                            self.diff = Some(match &prompt {
+                                Prompt::Recover(lines) => DiffView::new(&disk, lines),
+                                _ => DiffView::new(&disk, self.file.textarea.lines()),
+                            });
+                        }
+
+                        self.prompt = Some((prompt, mode));
+                        self.mode = Mode::Save;
+                    },
+
+                    (Prompt::Unsaved(msg), Choice::Save) => self.save(Some(msg)),
+
+                    (Prompt::Unsaved(msg), Choice::Discard) => self.leave(msg),
+
+                    (Prompt::Changed(then), Choice::Reload) => {
+                        if !self.reload() { return; }
+                        if let Some(msg) = then { self.leave(msg); }
+                    },
+
+                    (Prompt::Changed(then), Choice::Overwrite) => match self.file.overwrite(&self.settings) {
+                        Ok(()) => {
+                            self.info = Some("File has been saved!".to_string());
+                            if let Some(msg) = then { self.leave(msg); }
+                        },
+
+                        Err(e) => self.info = Some(format!("Not saved: {e}")),
+                    },
+
+                    (Prompt::Recover(lines), Choice::Recover) => {
+                        self.file.recover(lines);
+                        self.info = Some("Recovered unsaved changes, save to keep them".to_string());
+                    },
+
+                    (Prompt::Recover(_), Choice::Discard) => self.file.remove_swap(),
+
+                    // Decided on the next time the note is opened
+                    (Prompt::Recover(_), Choice::Cancel) => self.file.keep_swap(),
+
+                    _ => {},
+                }
+            },
+
+            // The note on disk may have changed while away,
+            // leaving is a good time to save
+            Message::Focus(focused) => {
+                self.is_focused = focused;
+                self.update(if focused { Message::Check } else { Message::Autosave });
+            },
+
+            Message::Info(info) => {
+                self.info = Some(info);
+            },
+
+            // Saves as is, formatting would move the text being typed
+            Message::Autosave => {
+                if !self.can_autosave() { return; }
+
+                let settings = Settings { format_on_save: false, ..self.settings.clone() };
+
+                match self.file.save(&settings) {
+                    Ok(()) => self.info = Some("Autosaved".to_string()),
+
+                    Err(SaveError::Changed(_)) => {
+                        self.info = Some("Not autosaved, the file changed on disk".to_string());
+                        self.update(Message::Check);
+                    },
+
+                    Err(e) => self.info = Some(format!("Autosave failed: {e}")),
+                }
+            },
+
+            // A clean note follows the disk, changes are only replaced when asked to
+            Message::Check => {
+                if self.settings.check_interval > 0 {
+                    self.timers.set(Timer::Check, Duration::from_secs(self.settings.check_interval.into()));
+                }
+
+                if self.mode == Mode::Save || self.file.read_only.is_some() { return; }
+
+                let Some(stamp) = self.file.changed_on_disk() else { return };
+
+                if !self.file.is_dirty() {
+                    if self.reload() {
+                        self.info = Some("Reloaded, the file changed on disk".to_string());
+                    }
+                } else if self.conflict != Some(stamp) {
+                    self.conflict = Some(stamp);
+                    self.ask(Prompt::Changed(None));
+                }
+            },
+
+            // Keeps a copy of unsaved changes in case the app doesn't get to exit
+            Message::Swap => {
+                if self.settings.swap_interval == 0 { return; }
+
+                self.timers.set(Timer::Swap, Duration::from_secs(self.settings.swap_interval.into()));
+
+                if !self.file.is_dirty() { return; }
+
+                if let Err(e) = self.file.write_swap() {
+                    self.info = Some(format!("Swap file not written: {e}"));
+                }
+            },
+
+            Message::Expire => {
+                self.info = None;
+            },
+        }
+    }
+
+    fn command(&mut self, command: Command) {
+        let Command::Apply(operator, target, count) = command else {
+            match command {
+                Command::Move(motion, count) => vim::motion_to(&mut self.file.textarea, motion, count),
+
+                Command::Put(before, count) => {
+                    if !self.writable() { return; }
+
+                    self.file.begin();
+                    vim::put(&mut self.file, &self.register, before, count.unwrap_or(1));
+                    self.file.end();
+                    self.last_change = Some((command, Vec::new()));
+                },
+
+                // A new count replaces the one the change had
+                Command::Repeat(count) => {
+                    let Some((command, typed)) = self.last_change.clone() else { return };
+
+                    self.command(match (command, count) {
+                        (Command::Apply(operator, target, _), Some(_)) => Command::Apply(operator, target, count),
+                        (Command::Put(before, _), Some(_)) => Command::Put(before, count),
+                        (command, _) => command,
+                    });
+
+                    if self.typed.is_some() {
+                        for input in typed { self.update(Message::Type(input)); }
+                        self.update(Message::View);
+                    }
+                },
+
+                // The same key again leaves
+                Command::Visual(linewise) => {
+                    let mode = if linewise { Mode::VisualLine } else { Mode::Visual };
+
+                    if self.mode == mode {
+                        self.update(Message::View);
+                        return;
+                    }
+
+                    if self.mode == Mode::View {
+                        self.anchor = self.file.textarea.cursor();
+                        self.file.textarea.start_selection();
+                    }
+
+                    self.mode = mode;
+                },
+
+                Command::OtherEnd => {
+                    let cursor = self.file.textarea.cursor();
+                    self.select(cursor, self.anchor);
+                },
+
+                // Paragraphs are whole lines
+                Command::Select(object, around) => {
+                    let target = Target::Object(object, around);
+                    let Some(span) = vim::span(&mut self.file.textarea, Operator::Yank, target, None) else { return };
+
+                    if span.linewise {
+                        self.mode = Mode::VisualLine;
+                        self.select((span.start.0, 0), (span.end.0, 0));
+                    } else {
+                        self.select(span.start, (span.end.0, span.end.1.saturating_sub(1)));
+                    }
+                },
+
+                Command::Apply(..) => {},
+            }
+
+            return;
+        };
+
+        if operator != Operator::Yank && !self.writable() { return; }
+
+        let span = match target {
+            Target::Selection => {
+                let span = self.selection();
+                self.update(Message::View);
+                span
+            },
+            target => vim::span(&mut self.file.textarea, operator, target, count),
+        };
+
+        let Some(span) = span else { return };
+        let cursor = self.file.textarea.cursor();
+
+        if matches!(operator, Operator::Delete | Operator::Change | Operator::Yank) {
+            self.register = vim::text(self.file.textarea.lines(), &span);
+        }
+
+        self.file.begin();
+
+        match operator {
+            Operator::Delete => vim::delete(&mut self.file, &span, false),
+
+            // The group stays open for what's typed next
+            Operator::Change => {
+                vim::delete(&mut self.file, &span, true);
+                self.mode = Mode::Edit;
+                self.typed = Some(Vec::new());
+            },
+
+            // Stays put unless the text starts before the cursor
+            Operator::Yank => vim::jump(&mut self.file.textarea, if span.linewise {
+                (span.start.0.min(cursor.0), cursor.1)
+            } else {
+                span.start.min(cursor)
+            }),
+
+            // A count on a selection indents it that many times
+            Operator::Indent | Operator::Outdent => {
+                let times = if target == Target::Selection { count.unwrap_or(1).min(vim::MAX_REPEAT) } else { 1 };
+
+                for _ in 0..times { vim::indent(&mut self.file, &span, operator == Operator::Outdent); }
+            },
+
+            Operator::Emphasis => vim::emphasize(&mut self.file, &span),
+
+            Operator::Reflow => vim::reflow(&mut self.file, &span, self.settings.reflow_width as usize),
+        }
+
+        if operator != Operator::Change { self.file.end(); }
+
+        // Repeating a selection would need its size, so those aren't
+        match (operator, target) {
+            (Operator::Yank, _) => {},
+            (_, Target::Selection) => self.last_change = None,
+            _ => self.last_change = Some((command, Vec::new())),
+        }
+    }
+
+    fn select(&mut self, anchor: (usize, usize), cursor: (usize, usize)) {
+        self.file.textarea.cancel_selection();
+        vim::jump(&mut self.file.textarea, anchor);
+        self.file.textarea.start_selection();
+        vim::jump(&mut self.file.textarea, cursor);
+        self.anchor = anchor;
+    }
+
+    // What visual mode has selected, also under a prompt
+    fn selection(&self) -> Option<Span> {
+        let linewise = match self.note_mode() {
+            Mode::Visual => false,
+            Mode::VisualLine => true,
+            _ => return None,
+        };
+
+        let (lines, cursor) = (self.file.textarea.lines(), self.file.textarea.cursor());
+        Some(vim::selection(lines, self.anchor, cursor, linewise))
+    }
+
+    // Closes the edit `c` started, it's undone and repeated as one
+    fn end_change(&mut self) {
+        let Some(typed) = self.typed.take() else { return };
+
+        self.file.end();
+        if let Some((_, keys)) = &mut self.last_change { *keys = typed; }
+    }
+
+    fn scroll(&mut self, scroll: Scroll) {
+        match self.mode {
+            Mode::Find => {
+                let page = self.finder.preview.height as isize;
+
+                match scroll {
+                    Scroll::Rows(rows) => self.finder.preview.scroll(rows),
+                    Scroll::Pages(pages) => self.finder.preview.scroll(pages * page),
+                    Scroll::Top => self.finder.preview.scroll_to_top(),
+                    Scroll::Bottom => self.finder.preview.scroll_to_bottom(),
+                }
+            },
+
+            Mode::Save => {
+                let Some(diff) = &mut self.diff else { return };
+                let page = diff.height as isize;
+
+                match scroll {
+                    Scroll::Rows(rows) => diff.scroll(rows),
+                    Scroll::Pages(pages) => diff.scroll(pages * page),
+                    Scroll::Top => diff.scroll(isize::MIN),
+                    Scroll::Bottom => diff.scroll(isize::MAX),
+                }
+            },
+
+            Mode::View if !self.raw && !self.split => {
+                let page = self.preview.height as isize;
+
+                match scroll {
+                    Scroll::Rows(rows) => self.preview.scroll(rows),
+                    Scroll::Pages(pages) => self.preview.scroll(pages * page),
+                    Scroll::Top => self.preview.scroll_to_top(),
+                    Scroll::Bottom => self.preview.scroll_to_bottom(),
+                }
+            },
+
+            // Allows scrolling past the last line
+            _ => {
+                let page = self.viewport.height as isize;
+                let rows = self.file.textarea.lines().len() as isize;
+
+                let rows = match scroll {
+                    Scroll::Rows(rows) => rows,
+                    Scroll::Pages(pages) => pages * page,
+                    Scroll::Top => -rows,
+                    Scroll::Bottom => rows,
+                };
+
+                self.viewport.scroll(&mut self.file.textarea, rows);
+            },
+        }
+    }
+
+    // Saves, then does what was waiting on it
+    fn save(&mut self, then: Option<Message>) {
+        match self.file.save(&self.settings) {
+            Ok(()) => {
+                self.info = Some("File has been saved!".to_string());
+                if let Some(msg) = then { self.leave(msg); }
+            },
+
+            Err(SaveError::Changed(stamp)) => {
+                self.conflict = Some(stamp);
+                self.ask(Prompt::Changed(then));
+            },
+
+            Err(e) => self.info = Some(format!("Not saved: {e}")),
+        }
+    }
+
+    fn reload(&mut self) -> bool {
+        match self.file.reload() {
+            Ok(()) => {
+                // The selection was on the old text
+                if matches!(self.mode, Mode::Visual | Mode::VisualLine) { self.mode = Mode::View; }
+
+                self.info = None;
+                true
+            },
+
+            Err(e) => {
+                self.info = Some(format!("Not reloaded: {e}"));
+                false
+            },
+        }
+    }
+
+    fn ask(&mut self, prompt: Prompt) {
+        self.prompt = Some((prompt, self.mode));
+        self.diff = None;
+        self.mode = Mode::Save;
+    }
+
+    // A swap file left by a crash is offered back when its note opens
+    fn offer_recovery(&mut self) {
+        if let Some(lines) = self.file.recovery() {
+            self.ask(Prompt::Recover(lines));
+        }
+    }
+
+    // Autosaves don't happen behind a prompt
+    fn can_autosave(&self) -> bool {
+        self.settings.autosave && self.file.is_dirty() && self.file.read_only.is_none()
+            && self.mode != Mode::Save
+    }
+
+    // What a frame shows that can change without input
+    fn shown(&self) -> (Mode, Option<String>, u64) {
+        (self.mode, self.info.clone(), self.file.hash())
+    }
+
+    // Leaves the note without looking at unsaved changes,
+    // they were saved or discarded so the swap file goes
+    fn leave(&mut self, msg: Message) {
+        match msg {
+            // Replaces the note being edited with the one picked in the finder
+            Message::Pick => {
+                let Some(path) = self.finder.selection() else { return };
+
+                self.file.remove_swap();
+                self.file = File::load(path);
+                self.info = self.file.read_only.as_ref().map(|e| format!("Opened read-only: {e}"));
+                self.highlighter = Highlighter::default();
+                self.viewport = Viewport::default();
+                self.preview = Preview::default();
+                self.mode = Mode::View;
+                self.offer_recovery();
             },
 
             Message::Done => {
+                self.file.remove_swap();
                 self.mode = Mode::Exit;
             },
+
+            _ => {},
+        }
+    }
+
+    // What's under the finder or the prompt
+    fn note_mode(&self) -> Mode {
+        let mode = match (self.mode, &self.prompt) {
+            (Mode::Save, Some((_, mode))) => *mode,
+            (mode, _) => mode,
+        };
+
+        if mode == Mode::Find { self.last_mode } else { mode }
+    }
+
+    // Files that failed to load can't be changed, says why if so
+    fn writable(&mut self) -> bool {
+        match &self.file.read_only {
+            Some(e) => {
+                self.info = Some(format!("Read-only: {e}"));
+                false
+            },
+
+            None => true,
         }
     }
-}
\ No newline at end of file
+
+    // Table keys only apply while the cursor is in one
+    fn in_table(&self) -> bool {
+        let (row, _) = self.file.textarea.cursor();
+        table::table_at(self.file.textarea.lines(), row).is_some()
+    }
+
+    // When the full preview is about to be replaced by the source,
+    // bring the cursor to what was being read if it's out of sight
+    fn leave_preview(&mut self) {
+        let showing_preview = self.mode == Mode::View && !self.raw && !self.split;
+        if !showing_preview { return; }
+
+        let (row, col) = self.file.textarea.cursor();
+        let line = self.preview.rendered_line(row);
+        let visible = self.preview.scroll..self.preview.scroll + self.preview.height.max(1);
+
+        if !visible.contains(&line) {
+            let row = self.preview.source_line(self.preview.scroll);
+            self.file.textarea.move_cursor(CursorMove::Jump(row as u16, col as u16));
+        }
+    }
+}
+
+// Puts the terminal back when dropped, also while unwinding from a panic
+struct Restore;
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+// Undoes the setup in `Model::init`, harmless if some of it never happened
+fn restore() {
+    let _ = disable_raw_mode();
+    let _ = execute!(
+        std::io::stdout(),
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        DisableFocusChange,
+        Show,
+    );
+}
+
+// The panic message would be lost on the alternate screen, so leave it first
+fn restore_on_panic() {
+    let hook = std::panic::take_hook();
+
+    std::panic::set_hook(Box::new(move |info| {
+        restore();
+        hook(info);
+    }));
+}
+
+pub fn app_err(e: Box<dyn std::error::Error>) {
+    let cmd = clap::Command::new("open");
+
+    let err = clap::Error::raw(
+        clap::error::ErrorKind::Io,
+        format!("{e}\n")
+        ).with_cmd(&cmd);
+
+    let _ = err.print();
+}
+
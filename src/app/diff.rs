@@ -0,0 +1,139 @@
+use ratatui::prelude::*;
+
+// Line diff between two versions of a note
+
+// Unchanged lines shown around each change
+const CONTEXT: usize = 3;
+
+// The common subsequence table grows with the product of the lengths,
+// past this the changed part is shown as removed then added
+const MAX_CELLS: usize = 4_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+pub fn diff<'a>(old: &'a [String], new: &'a [String]) -> Vec<Change<'a>> {
+    let start = old.iter()
+        .zip(new)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let end = old[start..].iter().rev()
+        .zip(new[start..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let a = &old[start..old.len() - end];
+    let b = &new[start..new.len() - end];
+
+    let mut changes: Vec<Change> = old[..start].iter().map(|line| Change::Same(line)).collect();
+
+    if a.len() * b.len() > MAX_CELLS {
+        changes.extend(a.iter().map(|line| Change::Removed(line)));
+        changes.extend(b.iter().map(|line| Change::Added(line)));
+    } else {
+        // lcs[i][j]: longest common subsequence of a[i..] and b[j..]
+        let mut lcs = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                lcs[i][j] = if a[i] == b[j] {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+
+        while i < a.len() || j < b.len() {
+            if i < a.len() && j < b.len() && a[i] == b[j] {
+                changes.push(Change::Same(&a[i]));
+                i += 1;
+                j += 1;
+            } else if j == b.len() || (i < a.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+                changes.push(Change::Removed(&a[i]));
+                i += 1;
+            } else {
+                changes.push(Change::Added(&b[j]));
+                j += 1;
+            }
+        }
+    }
+
+    changes.extend(old[old.len() - end..].iter().map(|line| Change::Same(line)));
+    changes
+}
+
+// Changed lines with a few unchanged ones around them
+// Each hunk starts with the line numbers it begins at in both versions
+pub fn lines(changes: &[Change]) -> Vec<Line<'static>> {
+    let mut shown = vec![false; changes.len()];
+
+    for (i, change) in changes.iter().enumerate() {
+        if matches!(change, Change::Same(_)) { continue; }
+
+        let end = (i + CONTEXT).min(changes.len() - 1);
+        for show in &mut shown[i.saturating_sub(CONTEXT)..=end] { *show = true; }
+    }
+
+    let header = Style::new().fg(Color::Cyan);
+    let removed = Style::new().fg(Color::Red);
+    let added = Style::new().fg(Color::Green);
+
+    let (mut old, mut new) = (1, 1);
+    let mut lines = Vec::new();
+
+    for (i, change) in changes.iter().enumerate() {
+        if shown[i] {
+            if i == 0 || !shown[i - 1] {
+                lines.push(Line::styled(format!("@@ -{old} +{new} @@"), header));
+            }
+
+            lines.push(match change {
+                Change::Same(line) => Line::raw(format!("  {line}")),
+                Change::Removed(line) => Line::styled(format!("- {line}"), removed),
+                Change::Added(line) => Line::styled(format!("+ {line}"), added),
+            });
+        }
+
+        match change {
+            Change::Same(_) => { old += 1; new += 1; },
+            Change::Removed(_) => old += 1,
+            Change::Added(_) => new += 1,
+        }
+    }
+
+    lines
+}
+
+// Diff shown in a prompt
+#[derive(Debug, Default)]
+pub struct DiffView {
+    pub lines: Vec<Line<'static>>,
+    pub scroll: usize,
+    // Rows it had on screen last frame
+    pub height: usize,
+}
+
+impl DiffView {
+    pub fn new(old: &[String], new: &[String]) -> DiffView {
+        let mut lines = lines(&diff(old, new));
+
+        if lines.is_empty() {
+            lines.push(Line::raw("No differences"));
+        }
+
+        DiffView { lines, ..DiffView::default() }
+    }
+
+    pub fn scroll(&mut self, rows: isize) {
+        let last = self.lines.len().saturating_sub(self.height);
+        self.scroll = self.scroll.saturating_add_signed(rows).min(last);
+    }
+}
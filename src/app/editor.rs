@@ -0,0 +1,438 @@
+use std::ops::Range;
+
+use ratatui::{prelude::*, widgets::*};
+use tui_textarea::{CursorMove, TextArea};
+use unicode_width::UnicodeWidthChar;
+
+use super::markdown::highlight::Highlighter;
+use super::vim::Span as Selection;
+
+// Scroll position of the editor, kept between frames
+// Replaces the textarea's own viewport since the textarea
+// is no longer the widget being rendered
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Viewport {
+    pub row: usize,
+    // Visual row of `row` at the top when soft wrapping
+    pub sub: usize,
+    pub col: usize,
+    pub height: usize,
+    // Width lines were wrapped at in the last frame
+    pub wrap: Option<usize>,
+}
+
+impl Viewport {
+    // Scrolls by `rows`, keeping the cursor inside the viewport
+    pub fn scroll(&mut self, textarea: &mut TextArea, rows: isize) {
+        let Some(width) = self.wrap else {
+            let last = textarea.lines().len().saturating_sub(1);
+            self.row = self.row.saturating_add_signed(rows).min(last);
+
+            let (row, col) = textarea.cursor();
+            let bottom = self.row + self.height.max(1) - 1;
+            let clamped = row.clamp(self.row, bottom.max(self.row));
+
+            if clamped != row {
+                textarea.move_cursor(CursorMove::Jump(clamped as u16, col as u16));
+            }
+
+            return;
+        };
+
+        let rows_of = |row: usize| wrap_line(&textarea.lines()[row], width, textarea.tab_length());
+
+        let top = (self.row, self.sub);
+        (self.row, self.sub) = if rows >= 0 {
+            forward(top, rows as usize, textarea.lines().len(), rows_of)
+        } else {
+            back(top, rows.unsigned_abs(), rows_of)
+        };
+
+        // Move the cursor to the first/last visible row if it went out of sight
+        let (row, col) = textarea.cursor();
+        let cursor = (row, visual_row(&rows_of(row), col));
+        let bottom = forward((self.row, self.sub), self.height.max(1) - 1, textarea.lines().len(), rows_of);
+
+        let target = if cursor < (self.row, self.sub) {
+            (self.row, self.sub)
+        } else if cursor > bottom {
+            bottom
+        } else {
+            return;
+        };
+
+        let start = rows_of(target.0)[target.1].start;
+        textarea.move_cursor(CursorMove::Jump(target.0 as u16, start as u16));
+    }
+
+    // Moves the cursor one row up or down on screen
+    // Wrapped lines are walked through a row at a time
+    pub fn move_cursor(&self, textarea: &mut TextArea, down: bool) {
+        let Some(width) = self.wrap else {
+            textarea.move_cursor(if down { CursorMove::Down } else { CursorMove::Up });
+            return;
+        };
+
+        let tab_len = textarea.tab_length();
+        let lines = textarea.lines();
+        let (row, col) = textarea.cursor();
+
+        let rows = wrap_line(&lines[row], width, tab_len);
+        let sub = visual_row(&rows, col);
+        let x = display_width(&lines[row], rows[sub].start..col, tab_len);
+
+        let (row, sub) = match down {
+            true if sub + 1 < rows.len() => (row, sub + 1),
+            true if row + 1 < lines.len() => (row + 1, 0),
+            false if sub > 0 => (row, sub - 1),
+            false if row > 0 => (row - 1, wrap_line(&lines[row - 1], width, tab_len).len() - 1),
+            _ => return,
+        };
+
+        let rows = wrap_line(&lines[row], width, tab_len);
+        let col = column_at(&lines[row], &rows, sub, x, tab_len);
+
+        textarea.move_cursor(CursorMove::Jump(row as u16, col as u16));
+    }
+}
+
+// Splits a line into the char ranges shown on each row when soft wrapping
+// Lines break after whitespace when possible, in the middle of a word otherwise
+pub fn wrap_line(line: &str, width: usize, tab_len: u8) -> Vec<Range<usize>> {
+    let widths = char_widths(line, tab_len);
+    let width = width.max(1);
+
+    let mut rows = Vec::new();
+    let mut start = 0;
+    let mut row_width = 0;
+    let mut last_break = None;
+
+    for (i, c) in line.chars().enumerate() {
+        if row_width + widths[i] > width && i > start {
+            let end = match last_break {
+                Some(b) if b > start => b,
+                _ => i,
+            };
+
+            rows.push(start..end);
+            row_width = widths[end..i].iter().sum();
+            start = end;
+            last_break = None;
+        }
+
+        row_width += widths[i];
+
+        if c.is_whitespace() { last_break = Some(i + 1); }
+    }
+
+    rows.push(start..widths.len());
+    rows
+}
+
+// Display width of every char, tabs expand to the next tab stop
+fn char_widths(line: &str, tab_len: u8) -> Vec<usize> {
+    let tab_len = tab_len as usize;
+    let mut col = 0;
+
+    line.chars()
+        .map(|c| {
+            let w = match c {
+                '\t' if tab_len > 0 => tab_len - col % tab_len,
+                '\t' => 0,
+                c => c.width().unwrap_or(0),
+            };
+
+            col += w;
+            w
+        })
+        .collect()
+}
+
+fn display_width(line: &str, chars: Range<usize>, tab_len: u8) -> usize {
+    char_widths(line, tab_len)[chars].iter().sum()
+}
+
+// Row of a wrapped line holding the char at `col`
+// The end of a row belongs to the next one, except for the last row
+fn visual_row(rows: &[Range<usize>], col: usize) -> usize {
+    rows.iter()
+        .position(|r| r.contains(&col))
+        .unwrap_or(rows.len() - 1)
+}
+
+// Char at display column `x` of a row, without leaving the row
+fn column_at(line: &str, rows: &[Range<usize>], sub: usize, x: usize, tab_len: u8) -> usize {
+    let widths = char_widths(line, tab_len);
+    let range = rows[sub].clone();
+    let last = if sub + 1 == rows.len() { range.end } else { range.end.saturating_sub(1).max(range.start) };
+
+    let mut used = 0;
+    for i in range {
+        if used + widths[i] > x { return i.min(last); }
+        used += widths[i];
+    }
+
+    last
+}
+
+fn forward<F>(mut pos: (usize, usize), n: usize, len: usize, rows_of: F) -> (usize, usize)
+where F: Fn(usize) -> Vec<Range<usize>> {
+    for _ in 0..n {
+        if pos.1 + 1 < rows_of(pos.0).len() {
+            pos.1 += 1;
+        } else if pos.0 + 1 < len {
+            pos = (pos.0 + 1, 0);
+        } else {
+            break;
+        }
+    }
+
+    pos
+}
+
+fn back<F>(mut pos: (usize, usize), n: usize, rows_of: F) -> (usize, usize)
+where F: Fn(usize) -> Vec<Range<usize>> {
+    for _ in 0..n {
+        if pos.1 > 0 {
+            pos.1 -= 1;
+        } else if pos.0 > 0 {
+            pos = (pos.0 - 1, rows_of(pos.0 - 1).len() - 1);
+        } else {
+            break;
+        }
+    }
+
+    pos
+}
+
+// Renders the textarea with the markdown highlights
+// Mirrors what tui_textarea draws: line numbers, cursor line and cursor
+pub struct Editor<'a> {
+    textarea: &'a TextArea<'a>,
+    highlighter: &'a Highlighter,
+    wrap: bool,
+    wrap_column: Option<usize>,
+    selection: Option<(Selection, Style)>,
+}
+
+impl<'a> Editor<'a> {
+    pub fn new(textarea: &'a TextArea<'a>, highlighter: &'a Highlighter) -> Editor<'a> {
+        Editor { textarea, highlighter, wrap: false, wrap_column: None, selection: None }
+    }
+
+    // Soft wraps at the pane width, or `column` if it's narrower
+    pub fn wrap(mut self, wrap: bool, column: Option<usize>) -> Editor<'a> {
+        self.wrap = wrap;
+        self.wrap_column = column;
+        self
+    }
+
+    // The textarea doesn't tell what it has selected, so it's passed in
+    pub fn selection(mut self, selection: Option<Selection>, style: Style) -> Editor<'a> {
+        self.selection = selection.map(|selection| (selection, style));
+        self
+    }
+
+    fn selected(&self, row: usize, col: usize) -> Option<Style> {
+        let (span, style) = self.selection?;
+
+        let selected = if span.linewise {
+            (span.start.0..=span.end.0).contains(&row)
+        } else {
+            (span.start..span.end).contains(&(row, col))
+        };
+
+        selected.then_some(style)
+    }
+
+    // A row of the screen showing the chars `chars` of line `row`
+    // Only the first row of a line gets its number
+    fn line(&self, row: usize, chars: Range<usize>, lnum_len: usize, skip: usize) -> Line<'a> {
+        let textarea = self.textarea;
+        let text = &textarea.lines()[row];
+        let (cursor_row, cursor_col) = textarea.cursor();
+        let is_cursor_line = row == cursor_row;
+        let is_last = chars.end == text.chars().count();
+
+        let mut spans = Vec::new();
+
+        if let Some(style) = textarea.line_number_style() {
+            let number = if chars.start == 0 { (row + 1).to_string() } else { String::new() };
+            spans.push(Span::styled(format!(" {number:>lnum_len$} "), style));
+        }
+
+        let base = if is_cursor_line { textarea.cursor_line_style() } else { Style::default() };
+        let highlights = self.highlighter.line(row);
+        let widths = char_widths(text, textarea.tab_length());
+
+        // Tab widths come from the whole line so they don't change when it wraps
+        let mut cells = Cells::new(skip);
+
+        for (i, (byte, c)) in text.char_indices().enumerate() {
+            if !chars.contains(&i) { continue; }
+
+            let mut style = highlights.iter()
+                .filter(|h| h.start <= byte && byte < h.end)
+                .fold(base, |style, h| style.patch(h.style));
+
+            if let Some(selected) = self.selected(row, i) {
+                style = style.patch(selected);
+            }
+
+            if is_cursor_line && i == cursor_col {
+                style = textarea.cursor_style();
+            }
+
+            if c == '\t' {
+                for _ in 0..widths[i] { cells.push(' ', 1, style); }
+            } else {
+                cells.push(c, widths[i], style);
+            }
+        }
+
+        // Cursor past the last char
+        if is_cursor_line && is_last && cursor_col >= chars.end {
+            cells.push(' ', 1, textarea.cursor_style());
+        } else if let Some(selected) = self.selected(row, 0).filter(|_| text.is_empty()) {
+            // Empty lines in the selection show one selected cell
+            cells.push(' ', 1, base.patch(selected));
+        }
+
+        spans.extend(cells.into_spans());
+        Line::from(spans)
+    }
+}
+
+// Groups chars with the same style into spans,
+// skipping the columns scrolled out on the left
+struct Cells {
+    skip: usize,
+    col: usize,
+    spans: Vec<Span<'static>>,
+    text: String,
+    style: Style,
+}
+
+impl Cells {
+    fn new(skip: usize) -> Cells {
+        Cells { skip, col: 0, spans: Vec::new(), text: String::new(), style: Style::default() }
+    }
+
+    fn push(&mut self, c: char, width: usize, style: Style) {
+        self.col += width;
+        if self.col <= self.skip { return; }
+
+        if style != self.style && !self.text.is_empty() {
+            self.spans.push(Span::styled(std::mem::take(&mut self.text), self.style));
+        }
+
+        // Wide chars cut by the scroll position are replaced by blanks
+        if self.col - width < self.skip {
+            self.text.extend(std::iter::repeat_n(' ', self.col - self.skip));
+        } else {
+            self.text.push(c);
+        }
+
+        self.style = style;
+    }
+
+    fn into_spans(mut self) -> Vec<Span<'static>> {
+        if !self.text.is_empty() {
+            self.spans.push(Span::styled(self.text, self.style));
+        }
+
+        self.spans
+    }
+}
+
+// Same rule as tui_textarea: only scroll when the cursor leaves the view
+fn next_scroll_top(prev_top: usize, cursor: usize, length: usize) -> usize {
+    if cursor < prev_top {
+        cursor
+    } else if prev_top + length <= cursor {
+        cursor + 1 - length
+    } else {
+        prev_top
+    }
+}
+
+fn num_digits(n: usize) -> usize {
+    n.to_string().len()
+}
+
+impl<'a> StatefulWidget for Editor<'a> {
+    type State = Viewport;
+
+    fn render(self, area: Rect, buf: &mut Buffer, viewport: &mut Viewport) {
+        let inner = match self.textarea.block() {
+            Some(block) => {
+                let inner = block.inner(area);
+                block.clone().render(area, buf);
+                inner
+            },
+            None => area,
+        };
+
+        let lines = self.textarea.lines();
+        let tab_len = self.textarea.tab_length();
+        let lnum_len = num_digits(lines.len());
+        let gutter = match self.textarea.line_number_style() {
+            Some(_) => lnum_len + 2,
+            None => 0,
+        };
+
+        let height = inner.height as usize;
+        let width = (inner.width as usize).saturating_sub(gutter);
+        let (cursor_row, cursor_col) = self.textarea.cursor();
+
+        viewport.height = height;
+
+        let text: Vec<Line> = if self.wrap {
+            let wrap = self.wrap_column.map_or(width, |column| column.min(width)).max(1);
+            let rows_of = |row: usize| wrap_line(&lines[row], wrap, tab_len);
+
+            // The top moves when the cursor row leaves the screen
+            let cursor = (cursor_row, visual_row(&rows_of(cursor_row), cursor_col));
+            let top = (viewport.row, viewport.sub);
+            let lowest_top = back(cursor, height.max(1) - 1, rows_of);
+
+            (viewport.row, viewport.sub) = if cursor < top { cursor } else { top.max(lowest_top) };
+            viewport.col = 0;
+            viewport.wrap = Some(wrap);
+
+            let mut text = Vec::with_capacity(height);
+            let mut row = viewport.row;
+            let mut sub = viewport.sub;
+
+            while text.len() < height && row < lines.len() {
+                let rows = rows_of(row);
+
+                for chars in rows.into_iter().skip(sub).take(height - text.len()) {
+                    text.push(self.line(row, chars, lnum_len, 0));
+                }
+
+                row += 1;
+                sub = 0;
+            }
+
+            text
+        } else {
+            let x = display_width(&lines[cursor_row], 0..cursor_col, tab_len);
+
+            viewport.row = next_scroll_top(viewport.row, cursor_row, height);
+            viewport.sub = 0;
+            viewport.col = next_scroll_top(viewport.col, x, width);
+            viewport.wrap = None;
+
+            let bottom = (viewport.row + height).min(lines.len());
+            (viewport.row..bottom)
+                .map(|row| self.line(row, 0..lines[row].chars().count(), lnum_len, viewport.col))
+                .collect()
+        };
+
+        Paragraph::new(text)
+            .style(self.textarea.style())
+            .alignment(self.textarea.alignment())
+            .render(inner, buf);
+    }
+}
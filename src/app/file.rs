@@ -1,29 +1,184 @@
-use tui_textarea::TextArea;
+use tui_textarea::{CursorMove, TextArea};
+use super::markdown::format;
+use super::swap;
+use crate::config::{LineEnding, Settings};
 use std::{
+    fmt,
+    fs,
+    hash::{
+        DefaultHasher,
+        Hash,
+        Hasher
+    },
+    ops::Range,
     path::{
         Path, 
         PathBuf
     }, 
-    io::Write
+    io::{self, Write},
+    time::SystemTime
 };
 
+// Edit made of several textarea operations, undone/redone as one
+// The content hashes tell if the group is still next in the history
+#[derive(Debug, Clone, Copy)]
+struct Group {
+    steps: usize,
+    before: u64,
+    after: u64,
+}
+
+// Why a file couldn't be loaded as it is on disk
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OpenError {
+    Directory,
+    // The bytes that aren't UTF-8 were replaced to show the rest
+    NotUtf8,
+    Io(io::ErrorKind, String),
+}
+
+impl fmt::Display for OpenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OpenError::Directory => write!(f, "it's a folder"),
+            OpenError::NotUtf8 => write!(f, "it isn't valid UTF-8, unknown bytes are shown as \u{FFFD}"),
+            OpenError::Io(_, e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for OpenError {}
+
+// Why a save didn't happen
+#[derive(Debug)]
+pub enum SaveError {
+    ReadOnly(OpenError),
+    // Something else wrote the file since it was loaded or saved
+    Changed(Stamp),
+    Io(io::Error),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::ReadOnly(e) => write!(f, "opened read-only, {e}"),
+            SaveError::Changed(_) => write!(f, "the file changed on disk"),
+            SaveError::Io(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<io::Error> for SaveError {
+    fn from(e: io::Error) -> SaveError {
+        SaveError::Io(e)
+    }
+}
+
+// The file on disk when it was last read or written
+// Its hash is only compared once the time or size moved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stamp {
+    modified: Option<SystemTime>,
+    len: u64,
+    hash: u64,
+}
+
+impl Stamp {
+    fn new(path: &Path, bytes: &[u8]) -> Stamp {
+        let mut hasher = DefaultHasher::new();
+        bytes.hash(&mut hasher);
+
+        Stamp {
+            modified: fs::metadata(path).and_then(|meta| meta.modified()).ok(),
+            len: bytes.len() as u64,
+            hash: hasher.finish(),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct File {
     pub path: PathBuf,
     pub name: String,
     pub textarea: TextArea<'static>,
+    // Set when the file on disk couldn't be loaded as is,
+    // saving would overwrite what the buffer never had
+    pub read_only: Option<OpenError>,
+    // How the file was laid out on disk, written back the same way
+    pub line_ending: LineEnding,
+    pub final_newline: bool,
+    pub bom: bool,
+    // Content hash of what's on disk, tells if there are unsaved changes
+    saved: u64,
+    // None while the file doesn't exist on disk
+    disk: Option<Stamp>,
+    // Copy of the unsaved changes kept in case the app dies, and the content hash last written to it
+    swap: Option<PathBuf>,
+    swapped: u64,
+    undo_groups: Vec<Group>,
+    redo_groups: Vec<Group>,
+    // Group still taking edits, see `begin`
+    group: Option<Group>,
 }
 
 impl File {
-	pub fn open(path: PathBuf) -> File {
-		// TODO: Check if file exists, create if not
-		// Create textarea
-		let mut textarea = TextArea::default();
+	// A file that doesn't exist yet opens empty
+	pub fn open(path: PathBuf) -> Result<File, OpenError> {
+        if path.is_dir() { return Err(OpenError::Directory); }
 
-		// Add content if any
-        if let Ok(content) = std::fs::read_to_string(&path) {
-            textarea = TextArea::new(content.lines().map(String::from).collect());
-        }
+        let (content, disk) = match std::fs::read(&path) {
+            Ok(bytes) => {
+                let disk = Stamp::new(&path, &bytes);
+                (String::from_utf8(bytes).map_err(|_| OpenError::NotUtf8)?, Some(disk))
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (String::new(), None),
+            Err(e) => return Err(OpenError::Io(e.kind(), e.to_string())),
+        };
+
+        let mut file = File::new(path, &content, None);
+
+        // New notes get one, an existing empty file stays empty
+        if disk.is_none() { file.final_newline = true; }
+        file.disk = disk;
+
+        Ok(file)
+	}
+
+	// Stand-in for a file `open` failed on
+	// Shows what could be decoded and refuses to be edited or saved
+	pub fn read_only(path: PathBuf, error: OpenError) -> File {
+        let content = match error {
+            OpenError::NotUtf8 => std::fs::read(&path)
+                .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+                .unwrap_or_default(),
+            _ => String::new(),
+        };
+
+        File::new(path, &content, Some(error))
+	}
+
+	// Opens the file, falling back to a read-only buffer
+	pub fn load(path: PathBuf) -> File {
+        File::open(path.clone()).unwrap_or_else(|e| File::read_only(path, e))
+	}
+
+    fn new(path: PathBuf, content: &str, read_only: Option<OpenError>) -> File {
+        let bom = content.starts_with('\u{FEFF}');
+        let content = content.strip_prefix('\u{FEFF}').unwrap_or(content);
+
+        // The most used ending wins when they're mixed
+        let crlf = content.matches("\r\n").count();
+        let lf = content.matches('\n').count() - crlf;
+        let line_ending = if crlf > lf { LineEnding::CrLf } else { LineEnding::Lf };
+
+        let final_newline = content.ends_with('\n');
+
+		// Create textarea
+        // Grouped edits take an entry per step, a change typed after `c` can take many
+        let mut textarea = TextArea::new(content.lines().map(String::from).collect());
+        textarea.set_max_histories(1000);
 
         // Extract name from path
         let name = Path::new(&path)
@@ -32,27 +187,429 @@ impl File {
             .unwrap_or("")
             .to_string();
 
+        // Read-only notes can't have changes to keep
+        let swap = if read_only.is_none() { swap::path(&path) } else { None };
+
         // Return Struct
-        File {
-            path: path,
-            name: name,
-            textarea: textarea,
+        let mut file = File {
+            path,
+            name,
+            textarea,
+            read_only,
+            line_ending,
+            final_newline,
+            bom,
+            saved: 0,
+            disk: None,
+            swap,
+            swapped: 0,
+            undo_groups: Vec::new(),
+            redo_groups: Vec::new(),
+            group: None,
+        };
+
+        file.saved = file.hash();
+        file.swapped = file.saved;
+        file
+    }
+
+	// Refuses to replace a version written by something else, see `overwrite`
+	// Formatting is part of the history, so it can be undone
+	pub fn save(&mut self, settings: &Settings) -> Result<(), SaveError> {
+        if let Some(e) = &self.read_only {
+            return Err(SaveError::ReadOnly(e.clone()));
+        }
+
+        if let Some(stamp) = self.changed_on_disk() {
+            return Err(SaveError::Changed(stamp));
+        }
+
+        self.overwrite(settings)
+	}
+
+	// Saves whatever is on disk now
+	pub fn overwrite(&mut self, settings: &Settings) -> Result<(), SaveError> {
+        if let Some(e) = &self.read_only {
+            return Err(SaveError::ReadOnly(e.clone()));
         }
+
+        if settings.format_on_save { self.format(); }
+
+        let content = self.contents(settings);
+
+        // Links are followed so the note they point to is the one replaced
+        let path = std::fs::canonicalize(&self.path).unwrap_or_else(|_| self.path.clone());
+
+        backup(&path, settings.backups)?;
+        write_atomic(&path, content.as_bytes())?;
+
+        self.saved = self.hash();
+        self.disk = Some(Stamp::new(&path, content.as_bytes()));
+        self.remove_swap();
+        Ok(())
+	}
+
+	// Reads the note again, the cursor stays where it was
+	pub fn reload(&mut self) -> Result<(), OpenError> {
+        let cursor = self.textarea.cursor();
+        let file = File::open(self.path.clone())?;
+
+        self.remove_swap();
+        *self = file;
+        self.restore_cursor(cursor);
+
+        Ok(())
 	}
 
-	// TODO: Rework [Was copy pasted from old code]
-	#[allow(dead_code)]
-	pub fn save(&self) -> std::io::Result<()> {
-        let path = std::path::Path::new(&self.path);
-        let file = std::fs::File::create(&path)?;
-        let mut buf_writer = std::io::BufWriter::new(file);
+	// Stamp of the version on disk if it isn't the one last read or written
+	// A file deleted since doesn't count, saving brings it back
+	pub fn changed_on_disk(&mut self) -> Option<Stamp> {
+        let meta = fs::metadata(&self.path).ok()?;
 
-        for line in self.textarea.lines() {
-            buf_writer.write_all(line.as_bytes())?;
-            buf_writer.write_all(b"\n")?; // Adding a newline after each line
+        if let Some(disk) = &self.disk {
+            if disk.modified == meta.modified().ok() && disk.len == meta.len() { return None; }
         }
 
-        buf_writer.flush()?;
+        let stamp = Stamp::new(&self.path, &fs::read(&self.path).ok()?);
+
+        match &mut self.disk {
+            // Touched but the same, no need to read it again next time
+            Some(disk) if disk.hash == stamp.hash => {
+                *disk = stamp;
+                None
+            },
+
+            _ => Some(stamp),
+        }
+	}
+
+	// Lines of the version on disk, empty if there is none
+	pub fn on_disk(&self) -> Vec<String> {
+        let content = fs::read(&self.path).unwrap_or_default();
+        let content = String::from_utf8_lossy(&content);
+
+        content.strip_prefix('\u{FEFF}').unwrap_or(&content)
+            .lines()
+            .map(String::from)
+            .collect()
+	}
+
+	// Writes the buffer to the swap file when it changed since the last time
+	pub fn write_swap(&mut self) -> io::Result<()> {
+        let Some(swap) = &self.swap else { return Ok(()) };
+
+        let hash = self.hash();
+        if hash == self.swapped { return Ok(()); }
+
+        swap::write(swap, self.textarea.lines())?;
+        self.swapped = hash;
+
         Ok(())
 	}
-}
\ No newline at end of file
+
+	pub fn remove_swap(&mut self) {
+        if let Some(swap) = &self.swap { swap::remove(swap); }
+        self.swapped = self.hash();
+	}
+
+	// Leaves the swap file alone for the rest of the session,
+	// changes made meanwhile aren't kept in it
+	pub fn keep_swap(&mut self) {
+        self.swap = None;
+	}
+
+	// Lines a session that didn't exit cleanly left in the swap file
+	// A swap file with nothing the note doesn't have is removed
+	pub fn recovery(&mut self) -> Option<Vec<String>> {
+        let lines = swap::read(self.swap.as_ref()?)?;
+
+        if lines[..] == self.textarea.lines()[..] {
+            self.remove_swap();
+            return None;
+        }
+
+        Some(lines)
+	}
+
+	// Replaces the buffer with recovered lines as one undoable edit,
+	// they still have to be saved
+	pub fn recover(&mut self, lines: Vec<String>) {
+        let len = self.textarea.lines().len();
+
+        self.replace_lines(0..len, lines);
+        self.textarea.move_cursor(CursorMove::Top);
+	}
+
+    pub fn is_dirty(&self) -> bool {
+        self.hash() != self.saved
+    }
+
+    // Text written to disk, laid out like the file was unless the settings say otherwise
+    // Formatting always ends the file with a newline
+    pub fn contents(&self, settings: &Settings) -> String {
+        let ending = settings.line_ending.unwrap_or(self.line_ending).as_str();
+        let bom = settings.bom.unwrap_or(self.bom);
+        let final_newline = settings.final_newline
+            .unwrap_or(self.final_newline || settings.format_on_save);
+
+        let mut content = String::new();
+        if bom { content.push('\u{FEFF}'); }
+
+        content.push_str(&self.textarea.lines().join(ending));
+        if final_newline { content.push_str(ending); }
+
+        content
+    }
+
+    // Only the lines between the first and last change are replaced,
+    // the cursor stays where it was
+    fn format(&mut self) {
+        let lines = self.textarea.lines();
+        let mut formatted = format::format(lines);
+
+        // The textarea always holds one line
+        if formatted.is_empty() { formatted.push(String::new()); }
+
+        let start = lines.iter()
+            .zip(&formatted)
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        if start == lines.len() && start == formatted.len() { return; }
+
+        let end = lines[start..].iter().rev()
+            .zip(formatted[start..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        let cursor = self.textarea.cursor();
+        let (mut start, mut end) = (start, end);
+
+        // Lines can only be dropped along with a neighbour that stays
+        if start + end == lines.len() || start + end == formatted.len() {
+            if start > 0 { start -= 1; } else { end -= 1; }
+        }
+
+        let added = formatted[start..formatted.len() - end].to_vec();
+        self.replace_lines(start..lines.len() - end, added);
+
+        self.restore_cursor(cursor);
+    }
+
+    // Moves the cursor back, as close as the text now allows
+    fn restore_cursor(&mut self, (row, col): (usize, usize)) {
+        let row = row.min(self.textarea.lines().len() - 1);
+        let col = col.min(self.textarea.lines()[row].chars().count());
+        self.textarea.move_cursor(CursorMove::Jump(row as u16, col as u16));
+    }
+
+    pub fn hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.textarea.lines().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    // Replaces the lines in `range` with `lines` as a single undoable edit
+    // The cursor ends up after the inserted text
+    pub fn replace_lines(&mut self, range: Range<usize>, lines: Vec<String>) {
+        if self.textarea.lines()[range.clone()] == lines[..] { return; }
+
+        let opened = self.begin();
+        let last = range.end - 1;
+        let last_len = self.textarea.lines()[last].chars().count();
+
+        self.textarea.move_cursor(CursorMove::Jump(range.start as u16, 0));
+        self.textarea.start_selection();
+        self.textarea.move_cursor(CursorMove::Jump(last as u16, last_len as u16));
+
+        let deleted = self.textarea.delete_str(0);
+        self.step(deleted);
+        self.textarea.cancel_selection();
+        let inserted = self.textarea.insert_str(lines.join("\n"));
+        self.step(inserted);
+
+        if opened { self.end(); }
+    }
+
+    // Edits from here to `end` are undone and redone as one,
+    // false if a group was already open and this one is part of it
+    pub fn begin(&mut self) -> bool {
+        if self.group.is_some() { return false; }
+
+        self.group = Some(Group { steps: 0, before: self.hash(), after: 0 });
+        true
+    }
+
+    // Each textarea call that modifies the text pushes one entry to the history
+    pub fn step(&mut self, modified: bool) {
+        if let (true, Some(group)) = (modified, &mut self.group) { group.steps += 1; }
+    }
+
+    pub fn end(&mut self) {
+        let Some(mut group) = self.group.take() else { return };
+        if group.steps == 0 { return; }
+
+        group.after = self.hash();
+        self.redo_groups.clear();
+        self.undo_groups.push(group);
+    }
+
+    pub fn undo(&mut self) {
+        self.end();
+
+        match self.undo_groups.last() {
+            Some(group) if group.after == self.hash() => {
+                let group = self.undo_groups.pop().unwrap();
+                for _ in 0..group.steps { self.textarea.undo(); }
+
+                self.redo_groups.push(group);
+            },
+
+            _ => { self.textarea.undo(); },
+        }
+    }
+
+    pub fn redo(&mut self) {
+        self.end();
+
+        match self.redo_groups.last() {
+            Some(group) if group.before == self.hash() => {
+                let group = self.redo_groups.pop().unwrap();
+                for _ in 0..group.steps { self.textarea.redo(); }
+
+                self.undo_groups.push(group);
+            },
+
+            _ => { self.textarea.redo(); },
+        }
+    }
+}
+
+// Writes next to `path` first, then renames over it,
+// so a crash or a full disk can't leave the note half written
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = dir.join(format!(".{name}.{}.tmp", std::process::id()));
+
+    let result = (|| {
+        // A new note can go in a folder that doesn't exist yet
+        fs::create_dir_all(dir)?;
+
+        let mut file = fs::OpenOptions::new().write(true).create_new(true).open(&temp)?;
+        file.write_all(bytes)?;
+
+        if let Ok(meta) = fs::metadata(path) {
+            fs::set_permissions(&temp, meta.permissions())?;
+        }
+
+        file.sync_all()?;
+        fs::rename(&temp, path)?;
+
+        // The rename only lasts once the folder itself is synced
+        #[cfg(unix)]
+        if let Ok(dir) = fs::File::open(dir) { let _ = dir.sync_all(); }
+
+        Ok(())
+    })();
+
+    if result.is_err() { let _ = fs::remove_file(&temp); }
+
+    result
+}
+
+// Keeps the version about to be replaced as `note.md.bak`,
+// older ones move to `note.md.bak.2` up to `note.md.bak.<count>`
+fn backup(path: &Path, count: u8) -> io::Result<()> {
+    if count == 0 || !path.is_file() { return Ok(()); }
+
+    let name = |n: u8| {
+        let mut name = path.as_os_str().to_owned();
+        name.push(if n == 1 { ".bak".to_string() } else { format!(".bak.{n}") });
+        PathBuf::from(name)
+    };
+
+    for n in (1..count).rev() {
+        if name(n).exists() { fs::rename(name(n), name(n + 1))?; }
+    }
+
+    fs::copy(path, name(1))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Loads `bytes` from a file and saves it back untouched
+    fn round_trip(bytes: &[u8]) -> Vec<u8> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("note.md");
+        fs::write(&path, bytes).unwrap();
+
+        let mut file = File::load(path.clone());
+        file.save(&Settings::default()).unwrap();
+
+        fs::read(&path).unwrap()
+    }
+
+    #[test]
+    fn saving_keeps_crlf_line_endings() {
+        assert_eq!(round_trip(b"# Title\r\n\r\ntext\r\n"), b"# Title\r\n\r\ntext\r\n");
+    }
+
+    #[test]
+    fn saving_keeps_the_bom() {
+        assert_eq!(round_trip("\u{FEFF}# Title\n".as_bytes()), "\u{FEFF}# Title\n".as_bytes());
+    }
+
+    #[test]
+    fn saving_adds_no_final_newline_that_wasnt_there() {
+        assert_eq!(round_trip(b"# Title\ntext"), b"# Title\ntext");
+    }
+
+    #[test]
+    fn saving_leaves_an_empty_file_empty() {
+        assert_eq!(round_trip(b""), b"");
+    }
+
+    #[test]
+    fn formatting_on_save_leaves_rows_that_arent_a_table() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("note.md");
+        let text = "a | b | c\n--- | ---\n1 | 2 | 3\n";
+        fs::write(&path, text).unwrap();
+
+        let mut file = File::load(path.clone());
+        file.save(&Settings { format_on_save: true, ..Settings::default() }).unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), text);
+    }
+
+    #[test]
+    fn a_new_note_ends_with_a_newline() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("new.md");
+
+        let mut file = File::load(path.clone());
+        file.textarea.insert_str("# New");
+        file.save(&Settings::default()).unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "# New\n");
+    }
+
+    #[test]
+    fn writing_creates_the_missing_folders() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ideas/2026/new.md");
+
+        write_atomic(&path, b"# New\n").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "# New\n");
+    }
+}
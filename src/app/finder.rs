@@ -0,0 +1,236 @@
+use std::{
+    fs,
+    io,
+    path::{Path, PathBuf},
+};
+use ratatui::{prelude::*, widgets::*};
+
+use crate::{fuzzy, notebook};
+use super::markdown::preview::{Preview, PreviewPane};
+
+// Ctrl-P popup listing the notes that fuzzy match the query
+#[derive(Debug, Default)]
+pub struct Finder {
+    root: PathBuf,
+    // Paths relative to the notes folder, latest edit first
+    notes: Vec<PathBuf>,
+    pub query: String,
+    pub hits: Vec<Hit>,
+    pub selected: usize,
+    scroll: usize,
+    // Note shown in the preview and its text
+    previewed: Option<PathBuf>,
+    source: Vec<String>,
+    pub preview: Preview,
+}
+
+#[derive(Debug, Clone)]
+pub struct Hit {
+    pub note: usize,
+    pub score: i64,
+    // Chars of the path that matched the query
+    pub positions: Vec<usize>,
+}
+
+impl Finder {
+    // Lists the notes again, they may have changed since the last time
+    // What can't be read is left out, a warning would draw over the screen
+    pub fn open(&mut self, root: PathBuf) -> io::Result<()> {
+        let mut notes: Vec<(PathBuf, u64)> = notebook::notes(&root, &mut |_, _| {})?
+            .into_iter()
+            .map(|path| {
+                let modified = fs::metadata(&path).map(|meta| notebook::modified(&meta)).unwrap_or(0);
+                let rel = path.strip_prefix(&root).unwrap_or(&path).to_path_buf();
+
+                (rel, modified)
+            })
+            .collect();
+
+        notes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        self.notes = notes.into_iter().map(|(path, _)| path).collect();
+        self.root = root;
+        self.query.clear();
+        self.filter();
+
+        Ok(())
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.query.push(c);
+        self.filter();
+    }
+
+    pub fn pop(&mut self) {
+        self.query.pop();
+        self.filter();
+    }
+
+    pub fn clear(&mut self) {
+        self.query.clear();
+        self.filter();
+    }
+
+    // Best match first, the order of `notes` breaks ties
+    fn filter(&mut self) {
+        let mut hits: Vec<Hit> = self.notes.iter()
+            .enumerate()
+            .filter_map(|(note, path)| {
+                let (score, positions) = fuzzy::score(&self.query, &path.to_string_lossy())?;
+                Some(Hit { note, score, positions })
+            })
+            .collect();
+
+        hits.sort_by(|a, b| b.score.cmp(&a.score).then(a.note.cmp(&b.note)));
+
+        self.hits = hits;
+        self.selected = 0;
+        self.scroll = 0;
+    }
+
+    pub fn select(&mut self, rows: isize) {
+        let last = self.hits.len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(rows).min(last);
+    }
+
+    pub fn path(&self, hit: &Hit) -> &Path {
+        &self.notes[hit.note]
+    }
+
+    // Full path of the selected note
+    pub fn selection(&self) -> Option<PathBuf> {
+        let hit = self.hits.get(self.selected)?;
+        Some(self.root.join(self.path(hit)))
+    }
+
+    // Reads the selected note for the preview when the selection changes
+    fn load_preview(&mut self) {
+        let selection = self.selection();
+        if selection == self.previewed { return; }
+
+        self.source = selection.as_ref()
+            .and_then(|path| fs::read(path).ok())
+            .map(|content| String::from_utf8_lossy(&content).lines().map(String::from).collect())
+            .unwrap_or_default();
+
+        self.preview.scroll_to_top();
+        self.previewed = selection;
+    }
+}
+
+#[derive(Default)]
+pub struct FinderPopup {
+    style: Style,
+}
+
+impl FinderPopup {
+    pub fn style(mut self, style: Style) -> FinderPopup {
+        self.style = style;
+        self
+    }
+}
+
+impl StatefulWidget for FinderPopup {
+    type State = Finder;
+
+    fn render(self, area: Rect, buf: &mut Buffer, finder: &mut Finder) {
+        // Centered, leaving the editor visible around it
+        let [_, area, _] = Layout::vertical([
+            Constraint::Percentage(10),
+            Constraint::Percentage(80),
+            Constraint::Percentage(10),
+        ]).areas(area);
+
+        let [_, area, _] = Layout::horizontal([
+            Constraint::Percentage(5),
+            Constraint::Percentage(90),
+            Constraint::Percentage(5),
+        ]).areas(area);
+
+        Clear.render(area, buf);
+
+        let [list_area, preview_area] = Layout::horizontal([
+            Constraint::Percentage(40),
+            Constraint::Percentage(60),
+        ]).areas(area);
+
+        let count = format!("[{}/{}]", finder.hits.len(), finder.notes.len());
+
+        let block = Block::new()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .style(self.style)
+            .title_top(Line::from("[FIND]").alignment(Alignment::Center))
+            .title_bottom(Line::from(count).alignment(Alignment::Right));
+
+        let inner = block.inner(list_area);
+        block.render(list_area, buf);
+
+        let [prompt, list] = Layout::vertical([
+            Constraint::Length(2),
+            Constraint::Fill(1),
+        ]).areas(inner);
+
+        Paragraph::new(format!("> {}", finder.query))
+            .style(Style::new().add_modifier(Modifier::BOLD))
+            .render(prompt, buf);
+
+        // Keep the selection in sight
+        let height = list.height as usize;
+        if finder.selected < finder.scroll {
+            finder.scroll = finder.selected;
+        } else if height > 0 && finder.selected >= finder.scroll + height {
+            finder.scroll = finder.selected + 1 - height;
+        }
+
+        let matched = Style::new().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+
+        let lines: Vec<Line> = finder.hits.iter()
+            .enumerate()
+            .skip(finder.scroll)
+            .take(height)
+            .map(|(i, hit)| {
+                let spans: Vec<Span> = finder.path(hit).to_string_lossy()
+                    .chars()
+                    .enumerate()
+                    .map(|(j, c)| {
+                        let style = if hit.positions.contains(&j) { matched } else { Style::new() };
+                        Span::styled(c.to_string(), style)
+                    })
+                    .collect();
+
+                let line = Line::from(spans);
+
+                if i == finder.selected {
+                    line.style(Style::new().add_modifier(Modifier::REVERSED))
+                } else {
+                    line
+                }
+            })
+            .collect();
+
+        Paragraph::new(lines).render(list, buf);
+
+        finder.load_preview();
+
+        let title = finder.previewed.as_ref()
+            .and_then(|path| path.file_stem())
+            .map(|name| format!("[{}]", name.to_string_lossy()))
+            .unwrap_or_default();
+
+        let preview_block = Block::new()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(Style::new().add_modifier(Modifier::DIM))
+            .padding(Padding::top(1))
+            .title_top(Line::from(title).alignment(Alignment::Center));
+
+        let width = preview_area.width.saturating_sub(2);
+        finder.preview.update(&finder.source, width);
+
+        PreviewPane::default()
+            .block(preview_block)
+            .style(self.style)
+            .render(preview_area, buf, &mut finder.preview);
+    }
+}
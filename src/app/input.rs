@@ -1,137 +1,276 @@
-use super::{Model, Mode, Message};
+use super::{Model, Mode, Message, Choice, Prompt, Query, Scroll};
+use super::markdown::table::Edit;
+use super::keymap::{Action, Layer};
 
-use crossterm::event::Event;
-use tui_textarea::{Input, Key};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind};
+use tui_textarea::{CursorMove, Input, Key};
 
 pub fn read_input(input: std::io::Result<Event>, model: &mut Model) {
-    let i = input.unwrap().clone();
+    let i = match input {
+        Ok(i) => i,
+
+        Err(e) => {
+            model.update(Message::Info(format!("Couldn't read input: {e}")));
+            return;
+        },
+    };
 
     match i {
-        Event::FocusGained => { model.is_focused = true },
+        Event::FocusGained => model.update(Message::Focus(true)),
 
-        Event::FocusLost => { model.is_focused = false },
+        Event::FocusLost => model.update(Message::Focus(false)),
 
         _ => {}
     }
 
-    // 
     match model.mode {
+        // Table keys come first while the cursor is in a table
         Mode::Edit => {
-            match Input::from(i) {
-                // Edit to View
-                Input { key: Key::Esc, .. } => {
-                    model.update(Message::View);
-                },
-
-                // Save
-                Input { key: Key::Char('s'), ctrl: true, .. } => {
-                    model.update(Message::Save);
-                },
-/*
-                // Undo
-                Input { key: Key::Char('z'), ctrl: true, .. } => {
-                    model.file.textarea.undo();
-                }
-
-                // Redo
-                Input { key: Key::Char('y'), ctrl: true, .. } => {
-                    model.file.textarea.redo();
-                }
-
-                // Copy
-                Input { key: Key::Char('c'), ctrl: true, .. } => {
-                    model.file.textarea.copy();
-                }
-
-                // Paste
-                Input { key: Key::Char('v'), ctrl: true, .. } => {
-                    // TODO:
-                    // I'm having trouble with this 
-                    // It does not copy/cut to clipboard and only pastes from clipboard
-                    unimplemented!()
-                }
-
-                // Cut
-                Input { key: Key::Char('x'), ctrl: true, .. } => {
-                    model.file.textarea.cut();
-                }
-*/
+            let input = to_input(i);
+
+            let action = model.in_table()
+                .then(|| model.keymap.action(Layer::Table, &input))
+                .flatten()
+                .or_else(|| model.keymap.action(Layer::Edit, &input));
+
+            match (action, input) {
+                (Some(action), _) => send(model, Layer::Edit, action),
+
+                // The textarea would scroll its own viewport, which is never drawn
+                (None, input @ Input { key: Key::MouseScrollDown | Key::MouseScrollUp, .. }) => {
+                    scroll_wheel(model, input);
+                },
+
                 // Read input in Edit Mode
-                input => {
-                    model.file.textarea.input(input);
-                }
+                (None, input) => model.update(Message::Type(input)),
+            }
+        },
+
+        // Rendered preview
+        Mode::View if !model.raw && !model.split => {
+            let input = Input::from(i);
+
+            match model.keymap.action(Layer::Preview, &input) {
+                Some(action) => send(model, Layer::Preview, action),
+
+                None => scroll_wheel(model, input),
             }
         },
 
-        // 
+        // Source
+        // Keys the keymap doesn't use are vim commands, they get every key once one is started
         Mode::View => {
-            match Input::from(i) {
-                // View to Exit
-                Input { key: Key::Esc, .. } |
-                Input { key: Key::Char('q'), .. } => { 
-                    model.update(Message::Done); 
+            let input = Input::from(i);
+            let action = model.keymap.action(Layer::Source, &input);
+
+            match input {
+                Input { key: Key::Char(c), ctrl: false, alt: false, .. } if !model.pending.is_empty() || action.is_none() => {
+                    model.update(Message::Normal(c));
                 },
 
-                // View to Edit
-                Input { key: Key::Char('i'), .. } | 
-                Input { key: Key::Enter, .. } => { 
-                    model.update(Message::Edit); 
+                _ if !model.pending.is_empty() => model.update(Message::Abort),
+
+                _ => match action {
+                    Some(action) => send(model, Layer::Source, action),
+
+                    // Allows scrolling past last line
+                    None => scroll_wheel(model, input),
+                },
+            }
+        },
+
+        // Vim keys move the cursor and apply operators to the selection,
+        // the other keys do what they do in the source. Letters bound to actions
+        // that would drop the selection stay vim keys, `i` picks a text object here
+        Mode::Visual | Mode::VisualLine => {
+            let input = Input::from(i);
+            let action = model.keymap.action(Layer::Source, &input)
+                .filter(|action| *action == Action::Quit || keeps_selection(*action) || !is_plain_char(&input));
+
+            match input {
+                Input { key: Key::Char(c), ctrl: false, alt: false, .. } if !model.pending.is_empty() || action.is_none() => {
+                    model.update(Message::Normal(c));
                 },
 
-                // Save
-                Input { key: Key::Char('s'), ctrl: true, .. } => {
-                    model.update(Message::Save);
+                _ if !model.pending.is_empty() => model.update(Message::Abort),
+
+                _ => match action {
+                    // Back to the source
+                    Some(Action::Quit) => model.update(Message::View),
+
+                    Some(action) if keeps_selection(action) => send(model, Layer::Source, action),
+
+                    Some(action) => {
+                        model.update(Message::View);
+                        send(model, Layer::Source, action);
+                    },
+
+                    None => scroll_wheel(model, input),
                 },
+            }
+        },
+
+        // Unsaved changes, changed on disk and recovery prompts
+        // Their keys are the letters shown in the prompt, so they aren't in the keymap
+        Mode::Save => {
+            let changed = matches!(model.prompt, Some((Prompt::Changed(_), _)));
+            let recover = matches!(model.prompt, Some((Prompt::Recover(_), _)));
 
-                // Go to start of ile - Shift + k
-                Input { key: Key::Char('K'), .. } => {
-                    model.file.textarea.move_cursor(tui_textarea::CursorMove::Top)
+            match Input::from(i) {
+                Input { key: Key::Char('s'), .. } |
+                Input { key: Key::Char('y'), .. } |
+                Input { key: Key::Enter, .. } if !changed && !recover => {
+                    model.update(Message::Confirm(Choice::Save));
                 },
 
-                // Go to end of file -  Shift + j
-                Input { key: Key::Char('J'), .. }  => {
-                    model.file.textarea.move_cursor(tui_textarea::CursorMove::Bottom)
+                Input { key: Key::Char('d'), .. } |
+                Input { key: Key::Char('n'), .. } if !changed => {
+                    model.update(Message::Confirm(Choice::Discard));
                 },
 
-                // Move Cursor Down
-                Input { key: Key::Char('j'), .. }
-                | Input { key: Key::Down, .. }  => {
-                    model.file.textarea.move_cursor(tui_textarea::CursorMove::Down)
+                Input { key: Key::Char('r'), .. } if changed => {
+                    model.update(Message::Confirm(Choice::Reload));
                 },
 
-                // Move Cursor Up
-                Input { key: Key::Char('k'), .. } |
-                Input { key: Key::Up, .. }  => {
-                    model.file.textarea.move_cursor(tui_textarea::CursorMove::Up)
+                Input { key: Key::Char('r'), .. } if recover => {
+                    model.update(Message::Confirm(Choice::Recover));
                 },
 
-                // Move Cursor Left
-                Input { key: Key::Char('h'), .. }
-                | Input { key: Key::Left, .. }  => {
-                    model.file.textarea.move_cursor(tui_textarea::CursorMove::Back)
+                Input { key: Key::Char('o'), .. } if changed => {
+                    model.update(Message::Confirm(Choice::Overwrite));
                 },
 
-                // Move Cursor Right
-                Input { key: Key::Char('l'), .. } |
-                Input { key: Key::Right, .. }  => {
-                    model.file.textarea.move_cursor(tui_textarea::CursorMove::Forward)
+                // Show or hide the diff between the disk and the buffer or swap file
+                Input { key: Key::Char('v'), .. } => {
+                    model.update(Message::Confirm(Choice::Diff));
                 },
 
-                // Scroll Down
-                // Allows scrolling past last line
+                Input { key: Key::Char('c'), .. } |
+                Input { key: Key::Esc, .. } => {
+                    model.update(Message::Confirm(Choice::Cancel));
+                },
+
+                // Scroll the diff
+                Input { key: Key::Char('j'), .. } |
+                Input { key: Key::Down, .. } |
                 Input { key: Key::MouseScrollDown, .. } => {
-                    model.file.textarea.scroll((1, 0))
-                }
+                    model.update(Message::Scroll(Scroll::Rows(1)))
+                },
 
-                // Scroll Up
+                Input { key: Key::Char('k'), .. } |
+                Input { key: Key::Up, .. } |
                 Input { key: Key::MouseScrollUp, .. } => {
-                    model.file.textarea.scroll((-1, 0));
-                }
+                    model.update(Message::Scroll(Scroll::Rows(-1)))
+                },
+
+                Input { key: Key::PageDown, .. } => {
+                    model.update(Message::Scroll(Scroll::Pages(1)))
+                },
+
+                Input { key: Key::PageUp, .. } => {
+                    model.update(Message::Scroll(Scroll::Pages(-1)))
+                },
 
                 _ => {}
             }
         },
 
+        Mode::Find => {
+            let input = Input::from(i);
+
+            match model.keymap.action(Layer::Find, &input) {
+                Some(action) => send(model, Layer::Find, action),
+
+                // Edit the query
+                None => if let Input { key: Key::Char(c), ctrl: false, alt: false, .. } = input {
+                    model.update(Message::Query(Query::Push(c)))
+                },
+            }
+        },
+
         _ => {}
     }
-}
\ No newline at end of file
+}
+
+// What a bound action does where it was pressed
+fn send(model: &mut Model, layer: Layer, action: Action) {
+    let message = match (layer, action) {
+        (_, Action::Quit) => Message::Done,
+        (_, Action::Edit) => Message::Edit,
+        (_, Action::View) => Message::View,
+        (_, Action::Save) => Message::Save,
+        (_, Action::Find) => Message::Find,
+        (_, Action::Split) => Message::Split,
+        (_, Action::Wrap) => Message::Wrap,
+        (_, Action::Source) => Message::Source,
+        (_, Action::Reflow) => Message::Reflow,
+        (_, Action::Undo) => Message::Undo,
+        (_, Action::Redo) => Message::Redo,
+
+        // The preview scrolls, the source and editor move the cursor
+        (Layer::Preview, Action::Top) => Message::Scroll(Scroll::Top),
+        (Layer::Preview, Action::Bottom) => Message::Scroll(Scroll::Bottom),
+        (Layer::Preview, Action::Down) => Message::Scroll(Scroll::Rows(1)),
+        (Layer::Preview, Action::Up) => Message::Scroll(Scroll::Rows(-1)),
+
+        // The finder moves its selection
+        (Layer::Find, Action::Down) => Message::Select(1),
+        (Layer::Find, Action::Up) => Message::Select(-1),
+
+        (_, Action::Top) => Message::Move(CursorMove::Top),
+        (_, Action::Bottom) => Message::Move(CursorMove::Bottom),
+        // By rows on screen when lines are wrapped
+        (_, Action::Down) => Message::MoveRow(true),
+        (_, Action::Up) => Message::MoveRow(false),
+        (_, Action::Left) => Message::Move(CursorMove::Back),
+        (_, Action::Right) => Message::Move(CursorMove::Forward),
+        (_, Action::PageDown) => Message::Scroll(Scroll::Pages(1)),
+        (_, Action::PageUp) => Message::Scroll(Scroll::Pages(-1)),
+
+        (_, Action::NextCell) => Message::Table(Edit::NextCell),
+        (_, Action::PrevCell) => Message::Table(Edit::PrevCell),
+        (_, Action::NextRow) => Message::Table(Edit::NextRow),
+        (_, Action::InsertRow) => Message::Table(Edit::InsertRow),
+        (_, Action::DeleteRow) => Message::Table(Edit::DeleteRow),
+        (_, Action::InsertColumn) => Message::Table(Edit::InsertColumn),
+        (_, Action::DeleteColumn) => Message::Table(Edit::DeleteColumn),
+        (_, Action::Align) => Message::Table(Edit::Align),
+
+        (_, Action::Open) => Message::Pick,
+        (_, Action::DeleteChar) => Message::Query(Query::Pop),
+        (_, Action::ClearQuery) => Message::Query(Query::Clear),
+    };
+
+    model.update(message);
+}
+
+// Moving, reflowing and changing the layout keep a visual selection
+fn keeps_selection(action: Action) -> bool {
+    matches!(action,
+        Action::Top | Action::Bottom | Action::Up | Action::Down | Action::Left | Action::Right
+        | Action::PageUp | Action::PageDown | Action::Reflow | Action::Split | Action::Wrap)
+}
+
+fn is_plain_char(input: &Input) -> bool {
+    matches!(input, Input { key: Key::Char(_), ctrl: false, alt: false, .. })
+}
+
+// The mouse wheel isn't in the keymap
+fn scroll_wheel(model: &mut Model, input: Input) {
+    match input.key {
+        Key::MouseScrollDown => model.update(Message::Scroll(Scroll::Rows(1))),
+        Key::MouseScrollUp => model.update(Message::Scroll(Scroll::Rows(-1))),
+        _ => {}
+    }
+}
+
+// Shift + Tab comes as its own key code, the textarea drops it
+fn to_input(event: Event) -> Input {
+    match event {
+        Event::Key(KeyEvent { code: KeyCode::BackTab, kind: KeyEventKind::Press, .. }) => {
+            Input { key: Key::Tab, shift: true, ..Input::default() }
+        },
+
+        event => Input::from(event),
+    }
+}
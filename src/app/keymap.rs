@@ -0,0 +1,390 @@
+use tui_textarea::{Input, Key};
+
+use crate::config::Bindings;
+
+// Keys are written like vim mappings: `q`, `K`, `<Esc>`, `<C-s>`, `<A-S-r>`, `<S-Tab>`
+// Named keys: Esc Enter Tab BS Del Up Down Left Right Home End PageUp PageDown Space lt F1..F12
+// C-, A- and S- add Ctrl, Alt and Shift, letters carry Shift in their case
+
+// Where a binding applies
+// Table bindings come first while the cursor is in a table in Edit mode,
+// the save, reload and recover prompts keep their own keys and have no layer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layer {
+    Preview,
+    Source,
+    Edit,
+    Table,
+    Find,
+}
+
+impl Layer {
+    const ALL: [Layer; 5] = [Layer::Preview, Layer::Source, Layer::Edit, Layer::Table, Layer::Find];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Layer::Preview => "preview",
+            Layer::Source => "source",
+            Layer::Edit => "edit",
+            Layer::Table => "table",
+            Layer::Find => "find",
+        }
+    }
+
+    // Plain keys type text here, binding them would take that away
+    fn types(self) -> bool {
+        matches!(self, Layer::Edit | Layer::Table | Layer::Find)
+    }
+
+    fn actions(self) -> &'static [Action] {
+        use Action::*;
+
+        match self {
+            Layer::Preview => &[Quit, Edit, Save, Find, Split, Source, Top, Bottom, Up, Down, PageUp, PageDown],
+            Layer::Source => &[
+                Quit, Edit, Save, Find, Split, Wrap, Source, Reflow, Undo, Redo,
+                Top, Bottom, Up, Down, Left, Right, PageUp, PageDown,
+            ],
+            Layer::Edit => &[View, Save, Find, Split, Wrap, Reflow, Undo, Redo, Up, Down, PageUp, PageDown],
+            Layer::Table => &[NextCell, PrevCell, NextRow, InsertRow, DeleteRow, InsertColumn, DeleteColumn, Align],
+            Layer::Find => &[Find, Open, Up, Down, PageUp, PageDown, DeleteChar, ClearQuery],
+        }
+    }
+
+    // The bindings before the config file changes any
+    fn defaults(self) -> &'static [(&'static str, Action)] {
+        use Action::*;
+
+        match self {
+            Layer::Preview => &[
+                ("<Esc>", Quit), ("q", Quit),
+                ("i", Edit), ("<Enter>", Edit),
+                ("<C-s>", Save),
+                ("<C-p>", Find),
+                ("<C-t>", Split),
+                ("<Tab>", Source),
+                ("K", Top), ("J", Bottom),
+                ("j", Down), ("<Down>", Down),
+                ("k", Up), ("<Up>", Up),
+                ("<PageDown>", PageDown), ("<PageUp>", PageUp),
+            ],
+
+            Layer::Source => &[
+                ("<A-q>", Reflow),
+                ("<Esc>", Quit), ("q", Quit),
+                ("i", Edit), ("<Enter>", Edit),
+                ("<C-s>", Save),
+                ("<C-p>", Find),
+                ("<C-t>", Split),
+                ("<A-z>", Wrap),
+                ("<Tab>", Source),
+                ("K", Top), ("J", Bottom),
+                ("j", Down), ("<Down>", Down),
+                ("k", Up), ("<Up>", Up),
+                ("h", Left), ("<Left>", Left),
+                ("l", Right), ("<Right>", Right),
+                ("<PageDown>", PageDown), ("<PageUp>", PageUp),
+                ("u", Undo), ("<C-r>", Redo),
+            ],
+
+            Layer::Edit => &[
+                ("<C-p>", Find),
+                ("<Esc>", View),
+                ("<C-s>", Save),
+                ("<C-t>", Split),
+                ("<A-z>", Wrap),
+                ("<Down>", Down), ("<Up>", Up),
+                ("<PageDown>", PageDown), ("<PageUp>", PageUp),
+                ("<C-v>", PageDown), ("<A-v>", PageUp),
+                ("<A-q>", Reflow),
+                ("<C-z>", Undo),
+                ("<C-y>", Redo),
+            ],
+
+            Layer::Table => &[
+                ("<Tab>", NextCell),
+                ("<S-Tab>", PrevCell),
+                ("<Enter>", NextRow),
+                ("<A-r>", InsertRow), ("<A-R>", DeleteRow),
+                ("<A-c>", InsertColumn), ("<A-C>", DeleteColumn),
+                ("<A-a>", Align),
+            ],
+
+            Layer::Find => &[
+                ("<Esc>", Find), ("<C-p>", Find),
+                ("<Enter>", Open),
+                ("<Up>", Up), ("<C-k>", Up),
+                ("<Down>", Down), ("<C-j>", Down),
+                ("<PageDown>", PageDown), ("<PageUp>", PageUp),
+                ("<BS>", DeleteChar),
+                ("<C-u>", ClearQuery),
+            ],
+        }
+    }
+
+    fn bindings(self, bindings: &Bindings) -> &std::collections::BTreeMap<String, String> {
+        match self {
+            Layer::Preview => &bindings.preview,
+            Layer::Source => &bindings.source,
+            Layer::Edit => &bindings.edit,
+            Layer::Table => &bindings.table,
+            Layer::Find => &bindings.find,
+        }
+    }
+}
+
+// What a key can be bound to, what it does depends on the layer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    Edit,
+    View,
+    Save,
+    Find,
+    Split,
+    Wrap,
+    Source,
+    Reflow,
+    Undo,
+    Redo,
+    Top,
+    Bottom,
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    NextCell,
+    PrevCell,
+    NextRow,
+    InsertRow,
+    DeleteRow,
+    InsertColumn,
+    DeleteColumn,
+    Align,
+    Open,
+    DeleteChar,
+    ClearQuery,
+}
+
+impl Action {
+    const NAMES: [(&'static str, Action); 30] = [
+        ("quit", Action::Quit),
+        ("edit", Action::Edit),
+        ("view", Action::View),
+        ("save", Action::Save),
+        ("find", Action::Find),
+        ("split", Action::Split),
+        ("wrap", Action::Wrap),
+        ("source", Action::Source),
+        ("reflow", Action::Reflow),
+        ("undo", Action::Undo),
+        ("redo", Action::Redo),
+        ("top", Action::Top),
+        ("bottom", Action::Bottom),
+        ("up", Action::Up),
+        ("down", Action::Down),
+        ("left", Action::Left),
+        ("right", Action::Right),
+        ("page-up", Action::PageUp),
+        ("page-down", Action::PageDown),
+        ("next-cell", Action::NextCell),
+        ("prev-cell", Action::PrevCell),
+        ("next-row", Action::NextRow),
+        ("insert-row", Action::InsertRow),
+        ("delete-row", Action::DeleteRow),
+        ("insert-column", Action::InsertColumn),
+        ("delete-column", Action::DeleteColumn),
+        ("align", Action::Align),
+        ("open", Action::Open),
+        ("delete-char", Action::DeleteChar),
+        ("clear-query", Action::ClearQuery),
+    ];
+
+    fn from_name(name: &str) -> Option<Action> {
+        Action::NAMES.iter().find(|(n, _)| *n == name).map(|(_, action)| *action)
+    }
+
+    fn name(self) -> &'static str {
+        Action::NAMES.iter().find(|(_, action)| *action == self).map_or("", |(name, _)| name)
+    }
+}
+
+// A key with its modifiers
+// Shift only counts for keys that aren't letters, those have it in their case
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Chord {
+    key: Key,
+    ctrl: bool,
+    alt: bool,
+    shift: bool,
+}
+
+impl Chord {
+    pub fn parse(chord: &str) -> Option<Chord> {
+        let mut chars = chord.chars();
+
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            return Some(Chord { key: Key::Char(c), ctrl: false, alt: false, shift: false });
+        }
+
+        let mut name = chord.strip_prefix('<')?.strip_suffix('>')?;
+        let (mut ctrl, mut alt, mut shift) = (false, false, false);
+
+        while let Some((modifier, rest)) = name.split_once('-').filter(|(_, rest)| !rest.is_empty()) {
+            match modifier {
+                "C" => ctrl = true,
+                "A" => alt = true,
+                "S" => shift = true,
+                _ => return None,
+            }
+
+            name = rest;
+        }
+
+        let key = match name {
+            "Esc" => Key::Esc,
+            "Enter" => Key::Enter,
+            "Tab" => Key::Tab,
+            "BS" => Key::Backspace,
+            "Del" => Key::Delete,
+            "Up" => Key::Up,
+            "Down" => Key::Down,
+            "Left" => Key::Left,
+            "Right" => Key::Right,
+            "Home" => Key::Home,
+            "End" => Key::End,
+            "PageUp" => Key::PageUp,
+            "PageDown" => Key::PageDown,
+            "Space" => Key::Char(' '),
+            "lt" => Key::Char('<'),
+            _ => match name.strip_prefix('F').and_then(|n| n.parse::<u8>().ok()) {
+                Some(n) if (1..=12).contains(&n) => Key::F(n),
+
+                _ => {
+                    let mut chars = name.chars();
+                    let (Some(c), None) = (chars.next(), chars.next()) else { return None };
+
+                    // <S-r> is R
+                    let c = if shift { c.to_uppercase().next().unwrap_or(c) } else { c };
+                    Key::Char(c)
+                },
+            },
+        };
+
+        Some(Chord::new(key, ctrl, alt, shift))
+    }
+
+    fn new(key: Key, ctrl: bool, alt: bool, shift: bool) -> Chord {
+        let shift = shift && !matches!(key, Key::Char(_));
+        Chord { key, ctrl, alt, shift }
+    }
+
+    pub fn of(input: &Input) -> Chord {
+        Chord::new(input.key, input.ctrl, input.alt, input.shift)
+    }
+
+    // Would type a char if it weren't bound
+    fn types(&self) -> bool {
+        matches!(self.key, Key::Char(_)) && !self.ctrl && !self.alt
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(Layer, Chord, Action)>,
+}
+
+// The built-in bindings
+impl Default for Keymap {
+    fn default() -> Keymap {
+        let bindings = Layer::ALL.iter()
+            .flat_map(|layer| layer.defaults().iter().map(move |(chord, action)| {
+                (*layer, Chord::parse(chord).expect("default chords parse"), *action)
+            }))
+            .collect();
+
+        Keymap { bindings }
+    }
+}
+
+impl Keymap {
+    // The defaults with the config file's bindings on top
+    // Action `none` removes a default. Every mistake is reported, not just the first
+    pub fn new(config: &Bindings) -> Result<Keymap, Vec<String>> {
+        let mut keymap = Keymap::default();
+        let mut errors = Vec::new();
+
+        for layer in Layer::ALL {
+            let mut seen: Vec<(Chord, &str)> = Vec::new();
+
+            for (text, name) in layer.bindings(config) {
+                let table = format!("[keymap.{}]", layer.name());
+
+                let Some(chord) = Chord::parse(text) else {
+                    errors.push(format!("{table}: '{text}' isn't a key, write keys like `q`, `<Esc>` or `<C-s>`"));
+                    continue;
+                };
+
+                if let Some((_, other)) = seen.iter().find(|(seen, _)| *seen == chord) {
+                    errors.push(format!("{table}: '{text}' and '{other}' are the same key"));
+                    continue;
+                }
+
+                seen.push((chord, text));
+
+                let action = match name.as_str() {
+                    "none" => None,
+
+                    name => match Action::from_name(name) {
+                        Some(action) if layer.actions().contains(&action) => Some(action),
+
+                        found => {
+                            let known: Vec<&str> = layer.actions().iter().map(|action| action.name()).collect();
+                            let problem = if found.is_some() { "can't be used in" } else { "is an unknown action in" };
+
+                            errors.push(format!(
+                                "{table}: '{name}' {problem} this mode, expected one of: none, {}",
+                                known.join(", ")
+                            ));
+                            continue;
+                        },
+                    },
+                };
+
+                if action.is_some() && layer.types() && chord.types() {
+                    errors.push(format!("{table}: '{text}' types text in this mode, add Ctrl or Alt to bind it"));
+                    continue;
+                }
+
+                keymap.bindings.retain(|(l, c, _)| !(*l == layer && *c == chord));
+
+                if let Some(action) = action {
+                    keymap.bindings.push((layer, chord, action));
+                }
+            }
+        }
+
+        if errors.is_empty() { Ok(keymap) } else { Err(errors) }
+    }
+
+    pub fn action(&self, layer: Layer, input: &Input) -> Option<Action> {
+        let chord = Chord::of(input);
+
+        self.bindings.iter()
+            .find(|(l, c, _)| *l == layer && *c == chord)
+            .map(|(_, _, action)| *action)
+    }
+}
+
+pub fn keymap_err(errors: Vec<String>) {
+    let cmd = clap::Command::new("config");
+
+    let err = clap::Error::raw(
+        clap::error::ErrorKind::InvalidValue,
+        format!("the keymap in the config file has mistakes\n  {}\n", errors.join("\n  "))
+        ).with_cmd(&cmd);
+
+    let _ = err.print();
+}
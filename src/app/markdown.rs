@@ -0,0 +1,101 @@
+use comrak::Options;
+use ratatui::style::{Color, Modifier, Style};
+
+pub mod format;
+pub mod highlight;
+pub mod preview;
+pub mod reflow;
+pub mod table;
+
+// Extensions shared by everything that parses the buffer
+// GFM tables and strikethrough are what my notes actually use
+pub fn options() -> Options {
+    let mut options = Options::default();
+
+    options.extension.table = true;
+    options.extension.strikethrough = true;
+    options.extension.tasklist = true;
+    options.extension.autolink = true;
+
+    options
+}
+
+// Headings get the same colors in the editor and in the preview
+pub fn heading_style(level: u8) -> Style {
+    let color = match level {
+        1 => Color::Magenta,
+        2 => Color::Cyan,
+        3 => Color::Blue,
+        4 => Color::Green,
+        5 => Color::Yellow,
+        _ => Color::Red,
+    };
+
+    Style::new().fg(color).add_modifier(Modifier::BOLD)
+}
+
+// Returns the fence char and length if the line opens/closes a code block
+pub fn fence(line: &str) -> Option<(char, usize)> {
+    let trimmed = line.trim_start();
+    if line.len() - trimmed.len() > 3 { return None; }
+
+    let c = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = trimmed.chars().take_while(|x| *x == c).count();
+
+    (len >= 3).then_some((c, len))
+}
+
+// A closing fence uses the same char, is at least as long and has no info string
+pub fn closes(open: (char, usize), line: &str) -> bool {
+    match fence(line) {
+        Some((c, len)) => c == open.0 && len >= open.1
+            && line.trim_start().trim_start_matches(c).trim().is_empty(),
+        None => false,
+    }
+}
+
+// Marks the lines of fenced code blocks, fences included
+pub fn fenced_lines(lines: &[String]) -> Vec<bool> {
+    let mut open = None;
+
+    lines.iter()
+        .map(|line| match open {
+            None => {
+                open = fence(line);
+                open.is_some()
+            },
+            Some(f) => {
+                if closes(f, line) { open = None; }
+                true
+            },
+        })
+        .collect()
+}
+
+pub fn is_thematic_break(text: &str) -> bool {
+    let chars: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+
+    chars.len() >= 3
+        && ['*', '-', '_'].iter().any(|m| chars.iter().all(|c| c == m))
+}
+
+pub fn is_delimiter_row(text: &str) -> bool {
+    text.contains('|')
+        && text.contains('-')
+        && text.chars().all(|c| matches!(c, '|' | '-' | ':' | ' '))
+}
+
+// Length of the `>` markers in front of a line
+pub fn quote_prefix(line: &str) -> usize {
+    let mut len = 0;
+
+    loop {
+        let rest = &line[len..];
+        let trimmed = rest.trim_start_matches(' ');
+
+        if rest.len() - trimmed.len() > 3 || !trimmed.starts_with('>') { return len; }
+
+        len += rest.len() - trimmed.len() + 1;
+        if line[len..].starts_with(' ') { len += 1; }
+    }
+}
@@ -0,0 +1,233 @@
+use super::{fenced_lines, is_thematic_break, quote_prefix};
+use super::reflow::list_marker;
+use super::table::{tables, Table};
+
+// Tidies the buffer before it's written
+// Realigns tables, uses `-` for every bullet and drops trailing blank lines,
+// saving adds the single newline at the end
+pub fn format(lines: &[String]) -> Vec<String> {
+    let fenced = fenced_lines(lines);
+    let bullets = bullets(lines, &fenced);
+    let mut out = Vec::with_capacity(lines.len());
+    let mut tables = tables(lines).into_iter().peekable();
+    let mut row = 0;
+
+    while row < lines.len() {
+        if let Some(table) = tables.next_if(|table| table.start == row) {
+            match Table::parse(&lines[table.clone()]) {
+                Some(parsed) => out.extend(parsed.render()),
+                None => out.extend_from_slice(&lines[table.clone()]),
+            }
+
+            row = table.end;
+            continue;
+        }
+
+        let line = &lines[row];
+        out.push(match bullets[row] {
+            Some(marker) => format!("{}-{}", &line[..marker], &line[marker + 1..]),
+            None => line.clone(),
+        });
+        row += 1;
+    }
+
+    while out.last().is_some_and(|line| line.trim().is_empty()) {
+        out.pop();
+    }
+
+    out
+}
+
+// An open list item: where its content starts and the list it belongs to
+struct Item {
+    content: usize,
+    list: usize,
+}
+
+// A list is the run of sibling items using the same marker
+struct List {
+    marker: char,
+    // Next to a sibling list with another bullet, `-` would merge them
+    keep: bool,
+}
+
+// Byte offset of each `*` or `+` bullet that can become `-`
+// Indented code is told apart from nested items by the list items above it
+fn bullets(lines: &[String], fenced: &[bool]) -> Vec<Option<usize>> {
+    let mut lists: Vec<List> = Vec::new();
+    let mut open: Vec<Item> = Vec::new();
+    let mut items = vec![None; lines.len()];
+    let mut depth = 0;
+    // Indented code can't start right after a paragraph line
+    let mut after_text = false;
+
+    for (row, line) in lines.iter().enumerate() {
+        let quote = quote_prefix(line);
+        let rest = &line[quote..];
+        let indent = rest.len() - rest.trim_start_matches(' ').len();
+
+        // Lists don't carry over into or out of a quote
+        let line_depth = line[..quote].matches('>').count();
+        if line_depth != depth {
+            open.clear();
+            after_text = false;
+            depth = line_depth;
+        }
+
+        if rest.trim().is_empty() {
+            after_text = false;
+            continue;
+        }
+
+        if fenced[row] {
+            if !after_text { open.retain(|item| item.content <= indent); }
+            after_text = false;
+            continue;
+        }
+
+        let item = marker(rest);
+        let text = item.is_none() && !is_thematic_break(rest) && !rest.trim_start().starts_with('#');
+
+        // Whatever isn't indented into the last item closes it, unless it continues its paragraph
+        let closed = open.iter().position(|item| item.content > indent);
+        let sibling = match closed {
+            Some(i) if !after_text || !text => open.drain(i..).next(),
+            _ => None,
+        };
+
+        // Code, or more of the paragraph above
+        let base = open.last().map_or(0, |item| item.content);
+        if indent >= base + 4 { continue; }
+
+        let Some((c, content)) = item else {
+            after_text = text;
+            continue;
+        };
+
+        let list = match sibling {
+            Some(sibling) if lists[sibling.list].marker == c => sibling.list,
+
+            sibling => {
+                let bullet = |c: char| ['-', '*', '+'].contains(&c);
+
+                let keep = sibling.is_some_and(|sibling| {
+                    let other = &mut lists[sibling.list];
+                    let adjacent = bullet(other.marker) && bullet(c);
+                    other.keep |= adjacent;
+                    adjacent
+                });
+
+                lists.push(List { marker: c, keep });
+                lists.len() - 1
+            },
+        };
+
+        open.push(Item { content, list });
+        items[row] = Some((list, quote + indent));
+        after_text = true;
+    }
+
+    items.into_iter()
+        .map(|item| {
+            let (list, marker) = item?;
+            let list = &lists[list];
+
+            (!list.keep && ['*', '+'].contains(&list.marker)).then_some(marker)
+        })
+        .collect()
+}
+
+// The marker char of a list item, the delimiter for ordered ones,
+// and where its content starts
+fn marker(rest: &str) -> Option<(char, usize)> {
+    let mut len = list_marker(rest)?;
+
+    // Nested items line up with the text, not with a task box
+    let marker = rest[..len].trim_end();
+    if ["[ ]", "[x]", "[X]"].iter().any(|b| marker.ends_with(b)) { len -= 4; }
+
+    let c = rest.trim_start().chars().find(|c| !c.is_ascii_digit())?;
+
+    Some((c, len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(String::from).collect()
+    }
+
+    fn formatted(text: &str) -> String {
+        format(&lines(text)).join("\n")
+    }
+
+    #[test]
+    fn bullets_become_dashes() {
+        let text = "* one\n* two\n  + nested\n\ntext\n\n+ three";
+        assert_eq!(formatted(text), "- one\n- two\n  - nested\n\ntext\n\n- three");
+    }
+
+    #[test]
+    fn lists_with_other_bullets_stay_apart() {
+        let text = "- one\n* two\n* three\n\n+ four\n- five";
+        assert_eq!(formatted(text), text);
+
+        // An ordered list next to it doesn't merge
+        assert_eq!(formatted("1. one\n* two"), "1. one\n- two");
+    }
+
+    #[test]
+    fn nested_items_are_not_code() {
+        assert_eq!(formatted("1. one\n    * two\n    * three"), "1. one\n    - two\n    - three");
+        assert_eq!(formatted("- one\n\n      * code"), "- one\n\n      * code");
+    }
+
+    #[test]
+    fn indented_code_and_fences_are_left_alone() {
+        let text = "text\n\n    * code\n\n```\n* fenced\n```";
+        assert_eq!(formatted(text), text);
+
+        // Without a blank line it's more of the paragraph
+        assert_eq!(formatted("- one\n    * two"), "- one\n    - two");
+    }
+
+    #[test]
+    fn quotes_have_their_own_lists() {
+        assert_eq!(formatted("- one\n> * two\n> * three"), "- one\n> - two\n> - three");
+        assert_eq!(formatted("> - one\n> * two"), "> - one\n> * two");
+    }
+
+    #[test]
+    fn trailing_blank_lines_are_dropped() {
+        assert_eq!(format(&lines("text\n\n  \n")), ["text"]);
+        assert_eq!(format(&lines("\n\n")), Vec::<String>::new());
+    }
+
+    #[test]
+    fn tables_keep_their_alignment_markers() {
+        let text = "a|b|c|d\n-|:-|:-:|-:\n1|22|333|4444";
+
+        assert_eq!(formatted(text), "\
+| a   | b   |  c  |    d |
+| --- | :-- | :-: | ---: |
+| 1   | 22  | 333 | 4444 |");
+    }
+
+    #[test]
+    fn rows_that_arent_a_table_are_left_alone() {
+        let text = "a | b | c\n--- | ---\n1 | 2 | 3";
+        assert_eq!(formatted(text), text);
+    }
+
+    #[test]
+    fn wide_chars_take_two_columns() {
+        let text = "| 名前 | x |\n|-|-|\n| a | 日本語 |";
+
+        assert_eq!(formatted(text), "\
+| 名前 | x      |
+| ---- | ------ |
+| a    | 日本語 |");
+    }
+}
@@ -0,0 +1,314 @@
+use std::{collections::HashMap, ops::Range, rc::Rc};
+
+use comrak::{
+    nodes::{AstNode, ListType, NodeValue, Sourcepos},
+    parse_document,
+    Arena,
+};
+use ratatui::style::{Color, Modifier, Style};
+
+use super::{closes, fence};
+
+// Style applied to the bytes [start, end) of a line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Highlight {
+    pub start: usize,
+    pub end: usize,
+    pub style: Style,
+}
+
+// Highlights of every line of a chunk, in the order they must be patched
+type ChunkStyles = Rc<Vec<Vec<Highlight>>>;
+
+// Keeps the styles of the buffer in sync with the textarea
+// The buffer is cut into chunks that comrak can parse on their own,
+// only chunks whose text changed since the last update are parsed again
+#[derive(Debug, Default)]
+pub struct Highlighter {
+    lines: Vec<String>,
+    chunks: Vec<(Range<usize>, ChunkStyles)>,
+    cache: HashMap<String, ChunkStyles>,
+}
+
+impl Highlighter {
+    pub fn update(&mut self, lines: &[String]) {
+        if self.lines == lines { return; }
+
+        // Reference definitions resolve links in other chunks,
+        // so they are appended to every chunk that gets parsed
+        let refs: String = lines.iter()
+            .filter(|line| is_reference(line))
+            .map(|line| format!("\n{line}"))
+            .collect();
+
+        let mut cache = HashMap::new();
+        let mut chunks = Vec::new();
+
+        for range in split(lines) {
+            let mut key = lines[range.clone()].join("\n");
+            if !refs.is_empty() {
+                key.push_str("\n\n");
+                key.push_str(&refs);
+            }
+
+            let styles = match self.cache.remove(&key) {
+                Some(styles) => styles,
+                None => Rc::new(parse(&key, range.len())),
+            };
+
+            cache.insert(key, styles.clone());
+            chunks.push((range, styles));
+        }
+
+        // Only keep what the current buffer uses
+        self.cache = cache;
+        self.chunks = chunks;
+        self.lines = lines.to_vec();
+    }
+
+    pub fn line(&self, row: usize) -> &[Highlight] {
+        let i = self.chunks.partition_point(|(range, _)| range.end <= row);
+
+        match self.chunks.get(i) {
+            Some((range, styles)) if range.contains(&row) => &styles[row - range.start],
+            _ => &[],
+        }
+    }
+}
+
+fn is_blank(line: &str) -> bool {
+    line.trim().is_empty()
+}
+
+fn is_reference(line: &str) -> bool {
+    let trimmed = line.trim_start();
+
+    line.len() - trimmed.len() <= 3
+        && trimmed.starts_with('[')
+        && trimmed.contains("]:")
+}
+
+// Chunks start at an unindented line that follows a blank line outside of a fenced block
+// Indented lines after a blank line may still belong to a list item, so they never start one
+fn split(lines: &[String]) -> Vec<Range<usize>> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut open: Option<(char, usize)> = None;
+
+    for (i, line) in lines.iter().enumerate() {
+        let boundary = open.is_none()
+            && i > start
+            && is_blank(&lines[i - 1])
+            && !is_blank(line)
+            && !line.starts_with([' ', '\t']);
+
+        if boundary {
+            chunks.push(start..i);
+            start = i;
+        }
+
+        open = match open {
+            None => fence(line),
+            Some(f) if closes(f, line) => None,
+            open => open,
+        };
+    }
+
+    chunks.push(start..lines.len());
+    chunks
+}
+
+fn parse(text: &str, len: usize) -> Vec<Vec<Highlight>> {
+    let arena = Arena::new();
+    let root = parse_document(&arena, text, &super::options());
+
+    let lines: Vec<&str> = text.split('\n').collect();
+    let mut styles = Styles { lines: &lines, out: vec![Vec::new(); len] };
+
+    for node in root.children() {
+        styles.node(node);
+    }
+
+    styles.out
+}
+
+struct Styles<'a> {
+    lines: &'a [&'a str],
+    out: Vec<Vec<Highlight>>,
+}
+
+impl Styles<'_> {
+    fn node<'a>(&mut self, node: &'a AstNode<'a>) {
+        let pos = node.data.borrow().sourcepos;
+
+        match &node.data.borrow().value {
+            NodeValue::Heading(heading) => {
+                self.range(pos, super::heading_style(heading.level));
+            },
+
+            NodeValue::Emph => self.range(pos, Style::new().add_modifier(Modifier::ITALIC)),
+
+            NodeValue::Strong => self.range(pos, Style::new().add_modifier(Modifier::BOLD)),
+
+            NodeValue::Strikethrough => {
+                self.range(pos, Style::new().add_modifier(Modifier::CROSSED_OUT));
+            },
+
+            NodeValue::Code(code) => {
+                // comrak leaves the backticks out of the position
+                let mut pos = pos;
+                pos.start.column = pos.start.column.saturating_sub(code.num_backticks);
+                pos.end.column += code.num_backticks;
+
+                self.range(pos, Style::new().fg(Color::Yellow));
+            },
+
+            NodeValue::Link(_) | NodeValue::Image(_) => {
+                // The url is dimmed, the text gets underlined below
+                self.range(pos, Style::new().fg(Color::Blue).add_modifier(Modifier::DIM));
+            },
+
+            NodeValue::Text(_) if is_link(node) => {
+                self.range(pos, Style::new()
+                    .remove_modifier(Modifier::DIM)
+                    .add_modifier(Modifier::UNDERLINED));
+            },
+
+            NodeValue::Item(item) => {
+                self.list_marker(pos, item.list_type);
+            },
+
+            // Task items don't carry the list type, their parent does
+            NodeValue::TaskItem(_) => {
+                let kind = node.parent()
+                    .and_then(|list| match &list.data.borrow().value {
+                        NodeValue::List(list) => Some(list.list_type),
+                        _ => None,
+                    })
+                    .unwrap_or(ListType::Bullet);
+
+                self.list_marker(pos, kind);
+            },
+
+            NodeValue::BlockQuote => {
+                self.block_quote(pos);
+            },
+
+            NodeValue::CodeBlock(code) => {
+                self.range(pos, Style::new().fg(Color::Yellow));
+
+                if code.fenced {
+                    let dim = Style::new().add_modifier(Modifier::DIM);
+                    self.line(pos.start.line, dim);
+
+                    if pos.end.line > pos.start.line
+                        && fence(self.text(pos.end.line)).is_some() {
+                        self.line(pos.end.line, dim);
+                    }
+                }
+            },
+
+            NodeValue::Table(_) => {
+                self.table(pos);
+            },
+
+            NodeValue::TableRow(true) => {
+                self.range(pos, Style::new().add_modifier(Modifier::BOLD));
+            },
+
+            NodeValue::ThematicBreak | NodeValue::HtmlBlock(_) | NodeValue::HtmlInline(_) => {
+                self.range(pos, Style::new().fg(Color::DarkGray));
+            },
+
+            _ => {},
+        }
+
+        for child in node.children() {
+            self.node(child);
+        }
+    }
+
+    fn text(&self, line: usize) -> &str {
+        self.lines.get(line.wrapping_sub(1)).copied().unwrap_or("")
+    }
+
+    fn push(&mut self, line: usize, start: usize, end: usize, style: Style) {
+        let len = self.text(line).len();
+
+        // Positions outside of the chunk belong to the appended references
+        if let Some(out) = self.out.get_mut(line.wrapping_sub(1)) {
+            let (start, end) = (start.min(len), end.min(len));
+
+            if start < end {
+                out.push(Highlight { start, end, style });
+            }
+        }
+    }
+
+    fn line(&mut self, line: usize, style: Style) {
+        self.push(line, 0, usize::MAX, style);
+    }
+
+    // Sourcepos is 1-based with inclusive ends, columns are in bytes
+    fn range(&mut self, pos: Sourcepos, style: Style) {
+        for line in pos.start.line..=pos.end.line {
+            let start = if line == pos.start.line { pos.start.column.saturating_sub(1) } else { 0 };
+            let end = if line == pos.end.line { pos.end.column } else { usize::MAX };
+
+            self.push(line, start, end, style);
+        }
+    }
+
+    fn list_marker(&mut self, pos: Sourcepos, kind: ListType) {
+        let start = pos.start.column.saturating_sub(1);
+        let rest = self.text(pos.start.line).get(start..).unwrap_or("");
+        let len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+
+        let style = match kind {
+            ListType::Bullet => Style::new().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            ListType::Ordered => Style::new().fg(Color::Yellow),
+        };
+
+        self.push(pos.start.line, start, start + len, style);
+    }
+
+    fn block_quote(&mut self, pos: Sourcepos) {
+        let marker = Style::new().fg(Color::Green);
+        self.range(pos, Style::new().add_modifier(Modifier::ITALIC));
+
+        for line in pos.start.line..=pos.end.line {
+            let start = if line == pos.start.line { pos.start.column.saturating_sub(1) } else { 0 };
+
+            // Lazy continuation lines have no marker
+            if let Some(i) = self.text(line).get(start..).and_then(|rest| rest.find('>')) {
+                if self.text(line)[start..start + i].trim().is_empty() {
+                    self.push(line, start + i, start + i + 1, marker);
+                }
+            }
+        }
+    }
+
+    fn table(&mut self, pos: Sourcepos) {
+        let dim = Style::new().fg(Color::DarkGray);
+
+        // The delimiter row under the header isn't a node of its own
+        self.line(pos.start.line + 1, dim);
+
+        for line in pos.start.line..=pos.end.line {
+            let pipes: Vec<usize> = self.text(line)
+                .match_indices('|')
+                .map(|(i, _)| i)
+                .collect();
+
+            for i in pipes {
+                self.push(line, i, i + 1, dim);
+            }
+        }
+    }
+}
+
+fn is_link<'a>(node: &'a AstNode<'a>) -> bool {
+    node.ancestors().skip(1).any(|n| {
+        matches!(n.data.borrow().value, NodeValue::Link(_) | NodeValue::Image(_))
+    })
+}
@@ -0,0 +1,624 @@
+use std::ops::Range;
+
+use comrak::{
+    nodes::{AstNode, ListType, NodeValue, TableAlignment},
+    parse_document,
+    Arena,
+};
+use ratatui::{prelude::*, widgets::*};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+// Rendered markdown shown in View mode
+// Only rebuilt when the buffer or the width of the pane changes
+#[derive(Debug, Default)]
+pub struct Preview {
+    source: Vec<String>,
+    width: u16,
+    lines: Vec<Line<'static>>,
+    map: Vec<Mapping>,
+    pub scroll: usize,
+    pub height: usize,
+}
+
+// Lines of the source a block comes from and the lines it was rendered to
+// Both ranges are 0-based and end exclusive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mapping {
+    pub source: Range<usize>,
+    pub rendered: Range<usize>,
+}
+
+impl Preview {
+    pub fn update(&mut self, source: &[String], width: u16) {
+        if self.width == width && self.source == source { return; }
+
+        (self.lines, self.map) = render(&source.join("\n"), width as usize);
+        self.source = source.to_vec();
+        self.width = width;
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    // Last scroll position that still fills the pane
+    pub fn max_scroll(&self) -> usize {
+        self.len().saturating_sub(self.height.max(1))
+    }
+
+    pub fn scroll(&mut self, rows: isize) {
+        self.scroll = self.scroll.saturating_add_signed(rows).min(self.max_scroll());
+    }
+
+    pub fn scroll_to_top(&mut self) {
+        self.scroll = 0;
+    }
+
+    pub fn scroll_to_bottom(&mut self) {
+        self.scroll = self.max_scroll();
+    }
+
+    // Rendered line of a source line, inside the innermost block holding it
+    // Lines in between blocks go to the start of the next block
+    pub fn rendered_line(&self, row: usize) -> usize {
+        let inner = self.map.iter()
+            .filter(|m| m.source.contains(&row))
+            .min_by_key(|m| m.source.len());
+
+        match inner {
+            Some(m) => interpolate(row, &m.source, &m.rendered),
+            None => self.map.iter()
+                .filter(|m| m.source.start > row)
+                .map(|m| m.rendered.start)
+                .min()
+                .unwrap_or(self.len()),
+        }
+    }
+
+    // Inverse of `rendered_line`
+    pub fn source_line(&self, line: usize) -> usize {
+        let inner = self.map.iter()
+            .filter(|m| m.rendered.contains(&line))
+            .min_by_key(|m| m.rendered.len());
+
+        match inner {
+            Some(m) => interpolate(line, &m.rendered, &m.source),
+            None => self.map.iter()
+                .filter(|m| m.rendered.start > line)
+                .map(|m| m.source.start)
+                .min()
+                .unwrap_or(self.source.len().saturating_sub(1)),
+        }
+    }
+
+    // Scrolls so the rendered `row` of the source sits `offset` lines
+    // from the top, the same place the cursor has in the editor
+    pub fn follow(&mut self, row: usize, offset: usize) {
+        self.scroll = self.rendered_line(row)
+            .saturating_sub(offset)
+            .min(self.max_scroll());
+    }
+}
+
+// Maps a line of one range proportionally onto the other
+fn interpolate(line: usize, from: &Range<usize>, to: &Range<usize>) -> usize {
+    let offset = (line - from.start) * to.len() / from.len().max(1);
+    to.start + offset.min(to.len().saturating_sub(1))
+}
+
+#[derive(Default)]
+pub struct PreviewPane<'a> {
+    block: Option<Block<'a>>,
+    style: Style,
+}
+
+impl<'a> PreviewPane<'a> {
+    pub fn block(mut self, block: Block<'a>) -> PreviewPane<'a> {
+        self.block = Some(block);
+        self
+    }
+
+    pub fn style(mut self, style: Style) -> PreviewPane<'a> {
+        self.style = style;
+        self
+    }
+}
+
+impl<'a> StatefulWidget for PreviewPane<'a> {
+    type State = Preview;
+
+    fn render(self, area: Rect, buf: &mut Buffer, preview: &mut Preview) {
+        let inner = match self.block {
+            Some(block) => {
+                let inner = block.inner(area);
+                block.render(area, buf);
+                inner
+            },
+            None => area,
+        };
+
+        preview.height = inner.height as usize;
+        preview.scroll = preview.scroll.min(preview.max_scroll());
+
+        let bottom = (preview.scroll + preview.height).min(preview.len());
+        let text = preview.lines[preview.scroll..bottom].to_vec();
+
+        Paragraph::new(text)
+            .style(self.style)
+            .render(inner, buf);
+    }
+}
+
+pub fn render(source: &str, width: usize) -> (Vec<Line<'static>>, Vec<Mapping>) {
+    let arena = Arena::new();
+    let root = parse_document(&arena, source, &super::options());
+
+    let mut renderer = Renderer { width: width.max(1), lines: Vec::new(), map: Vec::new() };
+    renderer.children(root, &mut Prefix::default(), false);
+
+    (renderer.lines, renderer.map)
+}
+
+fn dim() -> Style {
+    Style::new().fg(Color::DarkGray)
+}
+
+fn code() -> Style {
+    Style::new().fg(Color::Yellow)
+}
+
+// What goes in front of every line of a block: list markers, quote bars
+// The first line of a list item gets the marker, the others its indentation
+#[derive(Debug, Clone, Default)]
+struct Prefix {
+    first: Option<Vec<Span<'static>>>,
+    rest: Vec<Span<'static>>,
+}
+
+impl Prefix {
+    fn next(&mut self) -> Vec<Span<'static>> {
+        self.first.take().unwrap_or_else(|| self.rest.clone())
+    }
+
+    fn width(&self) -> usize {
+        self.rest.iter().map(|span| span.width()).sum()
+    }
+
+    fn nest(&mut self, first: Span<'static>, rest: Span<'static>) -> Prefix {
+        let mut prefix = Prefix { first: Some(self.next()), rest: self.rest.clone() };
+
+        prefix.first.as_mut().unwrap().push(first);
+        prefix.rest.push(rest);
+
+        prefix
+    }
+}
+
+struct Renderer {
+    width: usize,
+    lines: Vec<Line<'static>>,
+    map: Vec<Mapping>,
+}
+
+impl Renderer {
+    fn push(&mut self, prefix: &mut Prefix, spans: Vec<Span<'static>>) {
+        let mut line = prefix.next();
+        line.extend(spans);
+
+        self.lines.push(Line::from(line));
+    }
+
+    fn blank(&mut self, prefix: &mut Prefix) {
+        let mut line = prefix.next();
+
+        // Don't leave trailing spaces behind the quote bars
+        if let Some(last) = line.last_mut() {
+            last.content = last.content.trim_end().to_string().into();
+        }
+
+        self.lines.push(Line::from(line));
+    }
+
+    fn available(&self, prefix: &Prefix) -> usize {
+        self.width.saturating_sub(prefix.width()).max(1)
+    }
+
+    fn children<'a>(&mut self, node: &'a AstNode<'a>, prefix: &mut Prefix, tight: bool) {
+        for (i, child) in node.children().enumerate() {
+            if i > 0 && !tight { self.blank(prefix); }
+
+            self.block(child, prefix);
+        }
+    }
+
+    fn block<'a>(&mut self, node: &'a AstNode<'a>, prefix: &mut Prefix) {
+        let start = self.lines.len();
+        self.render_block(node, prefix);
+
+        let pos = node.data.borrow().sourcepos;
+        let source = pos.start.line.saturating_sub(1)..pos.end.line;
+
+        if !source.is_empty() && start < self.lines.len() {
+            self.map.push(Mapping { source, rendered: start..self.lines.len() });
+        }
+    }
+
+    fn render_block<'a>(&mut self, node: &'a AstNode<'a>, prefix: &mut Prefix) {
+        match &node.data.borrow().value {
+            NodeValue::Paragraph => {
+                let segments = inlines(node, Style::default());
+                self.wrap(segments, prefix);
+            },
+
+            NodeValue::Heading(heading) => {
+                let style = super::heading_style(heading.level);
+                let start = self.lines.len();
+
+                self.wrap(inlines(node, style), prefix);
+
+                let underline = match heading.level {
+                    1 => "═",
+                    2 => "─",
+                    _ => return,
+                };
+
+                let width = self.lines[start..].iter()
+                    .map(|line| line.width().saturating_sub(prefix.width()))
+                    .max()
+                    .unwrap_or(0);
+
+                self.push(prefix, vec![Span::styled(underline.repeat(width), style)]);
+            },
+
+            NodeValue::List(list) => {
+                self.list(node, list.list_type, list.start, list.tight, prefix);
+            },
+
+            NodeValue::BlockQuote => {
+                let bar = Span::styled("│ ", Style::new().fg(Color::Green));
+                let mut prefix = prefix.nest(bar.clone(), bar);
+
+                self.children(node, &mut prefix, false);
+            },
+
+            NodeValue::CodeBlock(block) => {
+                let lang = block.info.split_whitespace().next().unwrap_or("");
+                self.code_block(&block.literal, lang, prefix);
+            },
+
+            NodeValue::Table(table) => {
+                self.table(node, &table.alignments, prefix);
+            },
+
+            NodeValue::ThematicBreak => {
+                let width = self.available(prefix);
+                self.push(prefix, vec![Span::styled("─".repeat(width), dim())]);
+            },
+
+            NodeValue::HtmlBlock(html) => {
+                for line in html.literal.lines() {
+                    self.push(prefix, vec![Span::styled(line.to_string(), dim())]);
+                }
+            },
+
+            _ => self.children(node, prefix, false),
+        }
+    }
+
+    fn wrap(&mut self, segments: Vec<Vec<Span<'static>>>, prefix: &mut Prefix) {
+        let width = self.available(prefix);
+
+        for segment in segments {
+            for line in wrap(segment, width) {
+                self.push(prefix, line);
+            }
+        }
+    }
+
+    fn list<'a>(
+        &mut self,
+        node: &'a AstNode<'a>,
+        kind: ListType,
+        start: usize,
+        tight: bool,
+        prefix: &mut Prefix
+    ) {
+        let depth = node.ancestors()
+            .filter(|n| matches!(n.data.borrow().value, NodeValue::List(_)))
+            .count();
+
+        for (i, item) in node.children().enumerate() {
+            if i > 0 && !tight { self.blank(prefix); }
+
+            let marker = match (&item.data.borrow().value, kind) {
+                (NodeValue::TaskItem(Some(_)), _) => "☑ ".to_string(),
+                (NodeValue::TaskItem(None), _) => "☐ ".to_string(),
+                (_, ListType::Ordered) => format!("{}. ", start + i),
+                (_, ListType::Bullet) => match depth % 3 {
+                    1 => "• ",
+                    2 => "◦ ",
+                    _ => "▪ ",
+                }.to_string(),
+            };
+
+            let indent = " ".repeat(marker.width());
+            let marker = Span::styled(marker, Style::new().fg(Color::Yellow));
+            let mut prefix = prefix.nest(marker, Span::raw(indent));
+
+            let start = self.lines.len();
+
+            if item.children().next().is_none() {
+                self.push(&mut prefix, Vec::new());
+            }
+
+            self.children(item, &mut prefix, tight);
+
+            let pos = item.data.borrow().sourcepos;
+            self.map.push(Mapping {
+                source: pos.start.line.saturating_sub(1)..pos.end.line,
+                rendered: start..self.lines.len(),
+            });
+        }
+    }
+
+    fn code_block(&mut self, literal: &str, lang: &str, prefix: &mut Prefix) {
+        let lines: Vec<String> = literal.lines()
+            .map(|line| line.replace('\t', "    "))
+            .collect();
+
+        let label = if lang.is_empty() { String::new() } else { format!(" {lang} ") };
+
+        // Box borders and padding take 4 columns
+        let max = self.available(prefix).saturating_sub(4).max(1);
+        let inner = lines.iter()
+            .map(|line| line.width())
+            .chain([label.width() + 1])
+            .max()
+            .unwrap_or(0)
+            .min(max);
+
+        let top = format!("╭─{label}{}╮", "─".repeat((inner + 1).saturating_sub(label.width())));
+        self.push(prefix, vec![Span::styled(top, dim())]);
+
+        for line in lines {
+            let line = truncate(&line, inner);
+            let pad = " ".repeat(inner - line.width());
+
+            self.push(prefix, vec![
+                Span::styled("│ ", dim()),
+                Span::styled(line, code()),
+                Span::raw(pad),
+                Span::styled(" │", dim()),
+            ]);
+        }
+
+        let bottom = format!("╰{}╯", "─".repeat(inner + 2));
+        self.push(prefix, vec![Span::styled(bottom, dim())]);
+    }
+
+    fn table<'a>(&mut self, node: &'a AstNode<'a>, alignments: &[TableAlignment], prefix: &mut Prefix) {
+        // Cells are kept on a single line, soft breaks can't happen in tables
+        let rows: Vec<(bool, Vec<Vec<Span<'static>>>)> = node.children()
+            .map(|row| {
+                let header = matches!(row.data.borrow().value, NodeValue::TableRow(true));
+                let cells = row.children()
+                    .map(|cell| inlines(cell, Style::default()).concat())
+                    .collect();
+
+                (header, cells)
+            })
+            .collect();
+
+        let mut widths = vec![1; alignments.len()];
+        for (_, cells) in &rows {
+            for (i, cell) in cells.iter().enumerate() {
+                let width: usize = cell.iter().map(|span| span.width()).sum();
+                if let Some(w) = widths.get_mut(i) { *w = width.max(*w); }
+            }
+        }
+
+        let border = |left: &str, mid: &str, right: &str| {
+            let parts: Vec<String> = widths.iter().map(|w| "─".repeat(w + 2)).collect();
+            vec![Span::styled(format!("{left}{}{right}", parts.join(mid)), dim())]
+        };
+
+        let top = border("┌", "┬", "┐");
+        let sep = border("├", "┼", "┤");
+        let bottom = border("└", "┴", "┘");
+
+        self.push(prefix, top);
+
+        for (header, cells) in rows {
+            let mut line = vec![Span::styled("│", dim())];
+
+            for (i, width) in widths.iter().enumerate() {
+                let mut cell = cells.get(i).cloned().unwrap_or_default();
+                let fill = width - cell.iter().map(|span| span.width()).sum::<usize>();
+
+                let (left, right) = match alignments.get(i) {
+                    Some(TableAlignment::Right) => (fill, 0),
+                    Some(TableAlignment::Center) => (fill / 2, fill - fill / 2),
+                    _ => (0, fill),
+                };
+
+                if header {
+                    for span in cell.iter_mut() {
+                        span.style = span.style.add_modifier(Modifier::BOLD);
+                    }
+                }
+
+                line.push(Span::raw(" ".repeat(left + 1)));
+                line.extend(cell);
+                line.push(Span::raw(" ".repeat(right + 1)));
+                line.push(Span::styled("│", dim()));
+            }
+
+            self.push(prefix, line);
+
+            if header { self.push(prefix, sep.clone()); }
+        }
+
+        self.push(prefix, bottom);
+    }
+}
+
+// Collects the inline content of a block as styled spans
+// Hard line breaks split the content in segments that are wrapped on their own
+fn inlines<'a>(node: &'a AstNode<'a>, style: Style) -> Vec<Vec<Span<'static>>> {
+    let mut segments = vec![Vec::new()];
+    collect(node, style, &mut segments);
+    segments
+}
+
+fn collect<'a>(node: &'a AstNode<'a>, style: Style, segments: &mut Vec<Vec<Span<'static>>>) {
+    for child in node.children() {
+        let mut push = |text: String, style: Style| {
+            segments.last_mut().unwrap().push(Span::styled(text, style));
+        };
+
+        match &child.data.borrow().value {
+            NodeValue::Text(text) => push(text.clone(), style),
+
+            NodeValue::Code(c) => push(c.literal.clone(), style.patch(code())),
+
+            NodeValue::HtmlInline(html) => push(html.clone(), style.patch(dim())),
+
+            NodeValue::SoftBreak => push(" ".to_string(), style),
+
+            NodeValue::LineBreak => segments.push(Vec::new()),
+
+            NodeValue::FootnoteReference(footnote) => {
+                push(format!("[^{}]", footnote.name), style.patch(dim()));
+            },
+
+            NodeValue::Emph => collect(child, style.add_modifier(Modifier::ITALIC), segments),
+
+            NodeValue::Strong => collect(child, style.add_modifier(Modifier::BOLD), segments),
+
+            NodeValue::Strikethrough => {
+                collect(child, style.add_modifier(Modifier::CROSSED_OUT), segments);
+            },
+
+            NodeValue::Link(_) => {
+                collect(child, style.fg(Color::Blue).add_modifier(Modifier::UNDERLINED), segments);
+            },
+
+            NodeValue::Image(_) => {
+                push("🖼 ".to_string(), style);
+                collect(child, style.fg(Color::Blue).add_modifier(Modifier::ITALIC), segments);
+            },
+
+            _ => collect(child, style, segments),
+        }
+    }
+}
+
+// Word wraps styled spans at `width` columns
+// Words longer than a line are broken wherever they hit the edge
+pub fn wrap(spans: Vec<Span<'static>>, width: usize) -> Vec<Vec<Span<'static>>> {
+    let mut lines = Vec::new();
+    let mut line: Vec<Span<'static>> = Vec::new();
+    let mut line_width = 0;
+
+    fn append(line: &mut Vec<Span<'static>>, text: &str, style: Style) {
+        match line.last_mut() {
+            Some(last) if last.style == style => {
+                last.content = format!("{}{text}", last.content).into();
+            },
+            _ => line.push(Span::styled(text.to_string(), style)),
+        }
+    }
+
+    fn flush(lines: &mut Vec<Vec<Span<'static>>>, line: &mut Vec<Span<'static>>) {
+        if let Some(last) = line.last_mut() {
+            last.content = last.content.trim_end().to_string().into();
+        }
+
+        lines.push(std::mem::take(line));
+    }
+
+    for span in spans {
+        for word in words(&span.content) {
+            let w = word.width();
+            let is_space = word.starts_with(char::is_whitespace);
+
+            if is_space {
+                // Spaces are dropped at the start of a line
+                if line_width == 0 { continue; }
+
+                if line_width + w > width {
+                    flush(&mut lines, &mut line);
+                    line_width = 0;
+                    continue;
+                }
+            } else if line_width + w > width && line_width > 0 {
+                flush(&mut lines, &mut line);
+                line_width = 0;
+            }
+
+            if w > width {
+                for c in word.chars() {
+                    let cw = c.width().unwrap_or(0);
+
+                    if line_width + cw > width && line_width > 0 {
+                        flush(&mut lines, &mut line);
+                        line_width = 0;
+                    }
+
+                    append(&mut line, &c.to_string(), span.style);
+                    line_width += cw;
+                }
+            } else {
+                append(&mut line, word, span.style);
+                line_width += w;
+            }
+        }
+    }
+
+    if !line.is_empty() || lines.is_empty() {
+        flush(&mut lines, &mut line);
+    }
+
+    lines
+}
+
+// Splits text in runs of whitespace and runs of everything else
+fn words(text: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start = 0;
+
+    for (i, c) in text.char_indices().skip(1) {
+        let prev = text[..i].chars().next_back().unwrap();
+
+        if prev.is_whitespace() != c.is_whitespace() {
+            words.push(&text[start..i]);
+            start = i;
+        }
+    }
+
+    if start < text.len() { words.push(&text[start..]); }
+
+    words
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    if text.width() <= width { return text.to_string(); }
+
+    let mut out = String::new();
+    let mut used = 0;
+
+    for c in text.chars() {
+        let w = c.width().unwrap_or(0);
+        if used + w + 1 > width { break; }
+
+        out.push(c);
+        used += w;
+    }
+
+    out.push('…');
+    out
+}
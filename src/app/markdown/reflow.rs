@@ -0,0 +1,326 @@
+use std::ops::Range;
+
+use unicode_width::UnicodeWidthStr;
+
+use super::{fenced_lines, is_delimiter_row, is_thematic_break, quote_prefix};
+
+// Rewraps the paragraphs found in `range` at `width` columns
+// Returns the new lines for the whole range, structure that can't be
+// reflowed (code, tables, headings) is copied as is
+pub fn reflow(lines: &[String], range: Range<usize>, width: usize) -> Vec<String> {
+    let fenced = fenced_lines(lines);
+    let mut out = Vec::new();
+    let mut row = range.start;
+
+    while row < range.end {
+        match paragraph(lines, &fenced, row) {
+            Some(p) => {
+                // Only the part of the paragraph inside the range is touched
+                let p = p.start.max(range.start)..p.end.min(range.end);
+                out.extend(fill(&lines[p.clone()], width));
+                row = p.end;
+            },
+
+            None => {
+                out.push(lines[row].clone());
+                row += 1;
+            },
+        }
+    }
+
+    out
+}
+
+// Range of the paragraph holding `row`, if it's one that can be reflowed
+pub fn paragraph_at(lines: &[String], row: usize) -> Option<Range<usize>> {
+    paragraph(lines, &fenced_lines(lines), row)
+}
+
+fn paragraph(lines: &[String], fenced: &[bool], row: usize) -> Option<Range<usize>> {
+    if !is_text(lines, fenced, row) { return None; }
+
+    let depth = quote_depth(&lines[row]);
+    let joins = |row: usize| is_text(lines, fenced, row) && quote_depth(&lines[row]) == depth;
+
+    let mut start = row;
+    while start > 0 && list_marker(content(&lines[start])).is_none() && joins(start - 1) {
+        start -= 1;
+    }
+
+    let mut end = row + 1;
+    while end < lines.len() && joins(end) && list_marker(content(&lines[end])).is_none() {
+        end += 1;
+    }
+
+    // Indented code can't be told apart from a list continuation, leave both alone
+    let first = content(&lines[start]);
+    let indent = first.len() - first.trim_start().len();
+    if indent >= 4 && list_marker(first).is_none() { return None; }
+
+    Some(start..end)
+}
+
+// Lines that are part of a paragraph
+fn is_text(lines: &[String], fenced: &[bool], row: usize) -> bool {
+    let text = content(&lines[row]).trim();
+
+    let setext = lines.get(row + 1)
+        .map(|next| content(next).trim())
+        .is_some_and(|next| !next.is_empty() && next.chars().all(|c| c == '=' || c == '-'));
+
+    let table = text.starts_with('|') || is_delimiter_row(text)
+        || lines.get(row + 1).is_some_and(|next| is_delimiter_row(content(next).trim()));
+
+    !fenced[row]
+        && !text.is_empty()
+        && !text.starts_with('#')
+        && !text.starts_with('<')
+        && !setext
+        && !table
+        && !is_thematic_break(text)
+        && !text.chars().all(|c| c == '=' || c == '-')
+}
+
+fn quote_depth(line: &str) -> usize {
+    line[..quote_prefix(line)].matches('>').count()
+}
+
+fn content(line: &str) -> &str {
+    &line[quote_prefix(line)..]
+}
+
+// Length of the indentation, list marker and the spaces after it
+// Task boxes are part of the marker
+pub fn list_marker(line: &str) -> Option<usize> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    let rest = &line[indent..];
+
+    if is_thematic_break(rest) { return None; }
+
+    let marker = if rest.starts_with(['-', '*', '+']) {
+        1
+    } else {
+        let digits = rest.chars().take_while(char::is_ascii_digit).count();
+        let delimited = rest[digits..].starts_with(['.', ')']);
+
+        if digits == 0 || digits > 9 || !delimited { return None; }
+
+        digits + 1
+    };
+
+    let after = &rest[marker..];
+    let spaces = after.len() - after.trim_start_matches(' ').len();
+
+    if spaces == 0 && !after.is_empty() { return None; }
+
+    // More than 4 spaces start indented code in the item, only one belongs to the marker
+    let spaces = if spaces > 4 { 1 } else { spaces };
+    let mut len = indent + marker + spaces;
+
+    let task = &line[len..];
+    if ["[ ] ", "[x] ", "[X] "].iter().any(|b| task.starts_with(b)) {
+        len += 4;
+    }
+
+    Some(len)
+}
+
+// Words that would turn the paragraph into another block at the start of a line:
+// list markers, quotes, headings, fences and setext or thematic break rows
+fn starts_block(word: &str) -> bool {
+    list_marker(word).is_some()
+        || word.starts_with('>')
+        || (word.len() <= 6 && word.chars().all(|c| c == '#'))
+        || word.starts_with("```") || word.starts_with("~~~")
+        || ['=', '-', '*', '_'].iter().any(|m| word.chars().all(|c| c == *m))
+}
+
+// Words split at whitespace, a code span is one word so its spaces are kept
+fn words(text: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start = None;
+    let mut i = 0;
+
+    while let Some(c) = text[i..].chars().next() {
+        if c == '`' {
+            start.get_or_insert(i);
+
+            // Backticks without a closing run of the same length are plain text
+            let ticks = text[i..].chars().take_while(|c| *c == '`').count();
+            i = code_span_end(text, i + ticks, ticks).unwrap_or(i + ticks);
+            continue;
+        }
+
+        if c.is_whitespace() {
+            if let Some(start) = start.take() { words.push(&text[start..i]); }
+        } else {
+            start.get_or_insert(i);
+        }
+
+        i += c.len_utf8();
+    }
+
+    if let Some(start) = start { words.push(&text[start..]); }
+
+    words
+}
+
+// End of the first run of exactly `ticks` backticks from `from`
+fn code_span_end(text: &str, from: usize, ticks: usize) -> Option<usize> {
+    let mut i = from;
+
+    while let Some(offset) = text[i..].find('`') {
+        let run = i + offset;
+        let len = text[run..].chars().take_while(|c| *c == '`').count();
+
+        if len == ticks { return Some(run + len); }
+        i = run + len;
+    }
+
+    None
+}
+
+// Greedy fill of the words of a paragraph
+// Hard breaks (two trailing spaces or a backslash) are kept,
+// a line only breaks before a word that can start one
+fn fill(lines: &[String], width: usize) -> Vec<String> {
+    let quote = &lines[0][..quote_prefix(&lines[0])];
+    let first = content(&lines[0]);
+
+    let (first_prefix, rest_prefix) = match list_marker(first) {
+        Some(len) => (first[..len].to_string(), " ".repeat(first[..len].width())),
+        None => {
+            let indent = |line: &str| line[..line.len() - line.trim_start().len()].to_string();
+            let rest = lines.get(1).map_or_else(|| indent(first), |line| indent(content(line)));
+
+            (indent(first), rest)
+        },
+    };
+
+    let mut out = Vec::new();
+    let mut line = String::new();
+    let mut prefix = first_prefix.as_str();
+
+    for (i, text) in lines.iter().enumerate() {
+        let text = content(text);
+        let text = if i == 0 { &text[first_prefix.len()..] } else { text };
+
+        let hard_break = text.ends_with("  ") || text.ends_with('\\');
+
+        for word in words(text) {
+            let used = quote.width() + prefix.width() + line.width();
+
+            if !line.is_empty() && used + 1 + word.width() > width && !starts_block(word) {
+                out.push(format!("{quote}{prefix}{line}"));
+                line.clear();
+                prefix = &rest_prefix;
+            }
+
+            if !line.is_empty() { line.push(' '); }
+            line.push_str(word);
+        }
+
+        if hard_break && i + 1 < lines.len() {
+            let mark = if text.ends_with('\\') { "" } else { "  " };
+
+            out.push(format!("{quote}{prefix}{line}{mark}"));
+            line.clear();
+            prefix = &rest_prefix;
+        }
+    }
+
+    if !line.is_empty() || out.is_empty() {
+        out.push(format!("{quote}{prefix}{line}"));
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(String::from).collect()
+    }
+
+    fn reflow_all(text: &str, width: usize) -> Vec<String> {
+        let lines = lines(text);
+        reflow(&lines, 0..lines.len(), width)
+    }
+
+    #[test]
+    fn list_items_get_a_hanging_indent() {
+        let text = "- one two three four five six\n- [ ] seven eight nine ten";
+
+        assert_eq!(reflow_all(text, 14), lines("\
+- one two
+  three four
+  five six
+- [ ] seven
+      eight
+      nine ten"));
+    }
+
+    #[test]
+    fn quotes_keep_their_prefix() {
+        let text = "> one two three four\n> > five six seven";
+
+        assert_eq!(reflow_all(text, 12), lines("\
+> one two
+> three four
+> > five six
+> > seven"));
+    }
+
+    #[test]
+    fn hard_breaks_are_kept() {
+        let text = "one two  \nthree four\\\nfive six seven";
+
+        assert_eq!(reflow_all(text, 80), lines("\
+one two  
+three four\\
+five six seven"));
+    }
+
+    #[test]
+    fn no_line_starts_with_a_block_marker() {
+        let text = "a b - c d + e f 1. g h > i j # k l";
+
+        assert_eq!(reflow_all(text, 3), lines("\
+a b -
+c d +
+e f 1.
+g h >
+i j #
+k l"));
+    }
+
+    #[test]
+    fn code_spans_keep_their_spaces() {
+        let text = "run `a  b   c` then ``x ` y`` and a ` lone tick";
+
+        assert_eq!(reflow_all(text, 12), lines("\
+run
+`a  b   c`
+then
+``x ` y``
+and a ` lone
+tick"));
+    }
+
+    #[test]
+    fn only_paragraphs_in_the_range_change() {
+        let text = "# one two three\n\none two three\n\n```\none two three\n```";
+
+        assert_eq!(reflow_all(text, 8), lines("\
+# one two three
+
+one two
+three
+
+```
+one two three
+```"));
+    }
+}
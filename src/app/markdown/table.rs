@@ -0,0 +1,334 @@
+use std::ops::Range;
+
+use unicode_width::UnicodeWidthStr;
+
+use super::{fenced_lines, is_delimiter_row};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    None,
+    Left,
+    Center,
+    Right,
+}
+
+// A GFM table split into cells
+// The delimiter row only lives in `alignments`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    pub indent: String,
+    pub alignments: Vec<Align>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    // Parses the lines of a table: header, delimiter row and body
+    pub fn parse(lines: &[String]) -> Option<Table> {
+        let header = lines.first()?;
+        let delimiter = lines.get(1)?.trim();
+
+        if !is_delimiter_row(delimiter) { return None; }
+
+        // GFM only makes a table when the header and delimiter row have as many cells
+        let delimiters = split_row(delimiter);
+        if delimiters.len() != split_row(header.trim()).len() { return None; }
+
+        let indent = header[..header.len() - header.trim_start().len()].to_string();
+
+        let alignments = delimiters.iter()
+            .map(|cell| match (cell.starts_with(':'), cell.ends_with(':')) {
+                (true, true) => Align::Center,
+                (true, false) => Align::Left,
+                (false, true) => Align::Right,
+                (false, false) => Align::None,
+            })
+            .collect();
+
+        let rows = lines.iter()
+            .enumerate()
+            .filter(|(i, _)| *i != 1)
+            .map(|(_, line)| split_row(line.trim()))
+            .collect();
+
+        let mut table = Table { indent, alignments, rows };
+        table.normalize();
+
+        Some(table)
+    }
+
+    pub fn columns(&self) -> usize {
+        self.alignments.len()
+    }
+
+    // Every row gets as many cells as the widest one
+    pub fn normalize(&mut self) {
+        let columns = self.rows.iter()
+            .map(Vec::len)
+            .chain([self.alignments.len()])
+            .max()
+            .unwrap_or(0)
+            .max(1);
+
+        self.alignments.resize(columns, Align::None);
+
+        for row in self.rows.iter_mut() {
+            row.resize(columns, String::new());
+        }
+    }
+
+    // Display width of each column, delimiters need at least 3 dashes
+    pub fn widths(&self) -> Vec<usize> {
+        (0..self.columns())
+            .map(|i| self.rows.iter()
+                .map(|row| row[i].width())
+                .max()
+                .unwrap_or(0)
+                .max(3))
+            .collect()
+    }
+
+    // Lines of the aligned table
+    pub fn render(&self) -> Vec<String> {
+        let widths = self.widths();
+        let mut lines = Vec::with_capacity(self.rows.len() + 1);
+
+        for (i, row) in self.rows.iter().enumerate() {
+            let cells: Vec<String> = row.iter()
+                .zip(&widths)
+                .zip(&self.alignments)
+                .map(|((cell, width), align)| pad(cell, *width, *align))
+                .collect();
+
+            lines.push(format!("{}| {} |", self.indent, cells.join(" | ")));
+
+            if i == 0 {
+                let delimiters: Vec<String> = widths.iter()
+                    .zip(&self.alignments)
+                    .map(|(width, align)| delimiter(*width, *align))
+                    .collect();
+
+                lines.push(format!("{}| {} |", self.indent, delimiters.join(" | ")));
+            }
+        }
+
+        lines
+    }
+
+    // Applies `edit` with the cursor on `line` (counting the delimiter row) and `cell`
+    // Returns where the cursor goes in the rendered table, the line after it
+    // when the table was left
+    pub fn edit(&mut self, edit: Edit, line: usize, cell: usize) -> (usize, usize) {
+        let columns = self.columns();
+        let mut row = if line == 0 { 0 } else { line - 1 };
+        let mut cell = cell.min(columns - 1);
+
+        match edit {
+            Edit::NextCell if cell + 1 < columns => cell += 1,
+
+            Edit::NextCell => {
+                row += 1;
+                cell = 0;
+            },
+
+            Edit::PrevCell if cell > 0 => cell -= 1,
+
+            Edit::PrevCell if row > 0 => {
+                row -= 1;
+                cell = columns - 1;
+            },
+
+            Edit::PrevCell => {},
+
+            // Enter on an empty last row leaves the table
+            Edit::NextRow if row > 0 && row + 1 == self.rows.len()
+                && self.rows[row].iter().all(String::is_empty) => {
+                self.rows.pop();
+                return (self.rows.len() + 1, 0);
+            },
+
+            Edit::NextRow => row += 1,
+
+            Edit::InsertRow => {
+                row += 1;
+                self.rows.insert(row, vec![String::new(); columns]);
+            },
+
+            // The header can't go, the table would stop being one
+            Edit::DeleteRow if row > 0 => {
+                self.rows.remove(row);
+                row = row.min(self.rows.len() - 1);
+            },
+
+            Edit::DeleteRow => {},
+
+            Edit::InsertColumn => {
+                cell += 1;
+                self.alignments.insert(cell, Align::None);
+                for cells in self.rows.iter_mut() {
+                    cells.insert(cell, String::new());
+                }
+            },
+
+            Edit::DeleteColumn if columns > 1 => {
+                self.alignments.remove(cell);
+                for cells in self.rows.iter_mut() {
+                    cells.remove(cell);
+                }
+
+                cell = cell.min(columns - 2);
+            },
+
+            Edit::DeleteColumn => {},
+
+            Edit::Align => {
+                self.alignments[cell] = match self.alignments[cell] {
+                    Align::None => Align::Left,
+                    Align::Left => Align::Center,
+                    Align::Center => Align::Right,
+                    Align::Right => Align::None,
+                };
+            },
+        }
+
+        // Moving past the last row opens a new one
+        if row == self.rows.len() {
+            self.rows.push(vec![String::new(); columns]);
+        }
+
+        (if row == 0 { 0 } else { row + 1 }, cell)
+    }
+}
+
+// Changes made while the cursor is in a table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edit {
+    NextCell,
+    PrevCell,
+    NextRow,
+    InsertRow,
+    DeleteRow,
+    InsertColumn,
+    DeleteColumn,
+    Align,
+}
+
+fn pad(cell: &str, width: usize, align: Align) -> String {
+    let fill = width - cell.width();
+
+    let (left, right) = match align {
+        Align::Right => (fill, 0),
+        Align::Center => (fill / 2, fill - fill / 2),
+        _ => (0, fill),
+    };
+
+    format!("{}{cell}{}", " ".repeat(left), " ".repeat(right))
+}
+
+fn delimiter(width: usize, align: Align) -> String {
+    match align {
+        Align::None => "-".repeat(width),
+        Align::Left => format!(":{}", "-".repeat(width - 1)),
+        Align::Right => format!("{}:", "-".repeat(width - 1)),
+        Align::Center => format!(":{}:", "-".repeat(width - 2)),
+    }
+}
+
+// Char indices of the pipes that aren't escaped or inside inline code
+fn separators(line: &str) -> Vec<usize> {
+    let mut pipes = Vec::new();
+    let mut escaped = false;
+    let mut code = false;
+
+    for (i, c) in line.chars().enumerate() {
+        if c == '|' && !escaped && !code { pipes.push(i); }
+
+        if c == '`' && !escaped { code = !code; }
+        escaped = c == '\\' && !escaped;
+    }
+
+    pipes
+}
+
+// Splits a row into its trimmed cells
+pub fn split_row(line: &str) -> Vec<String> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut pipes = separators(line);
+
+    // Pipes at the ends close the row, they don't open empty cells
+    let start = if pipes.first() == Some(&0) { pipes.remove(0); 1 } else { 0 };
+    let end = if pipes.last().is_some_and(|pipe| pipe + 1 == chars.len()) {
+        pipes.pop().unwrap()
+    } else {
+        chars.len()
+    };
+
+    let bounds = [start].into_iter()
+        .chain(pipes.iter().map(|pipe| pipe + 1))
+        .zip(pipes.iter().copied().chain([end]));
+
+    bounds.map(|(a, b)| chars[a..b].iter().collect::<String>().trim().to_string())
+        .collect()
+}
+
+// Cell under the char column `col`
+pub fn cell_at(line: &str, col: usize) -> usize {
+    let leading = line.trim_start().starts_with('|') as usize;
+    let before = separators(line).iter().filter(|pipe| **pipe < col).count();
+
+    before.saturating_sub(leading)
+}
+
+// Char column where the text of `cell` starts in a rendered row
+pub fn cell_start(line: &str, cell: usize) -> usize {
+    let pipes = separators(line);
+    let Some(&pipe) = pipes.get(cell) else { return line.chars().count(); };
+    let next = pipes.get(cell + 1).copied().unwrap_or(usize::MAX);
+
+    let text = line.chars()
+        .enumerate()
+        .skip(pipe + 1)
+        .find(|(_, c)| *c != ' ')
+        .map(|(i, _)| i)
+        .filter(|i| *i < next);
+
+    // Empty cells put the cursor after the padding space
+    text.unwrap_or(pipe + 2)
+}
+
+// Whether a line has a pipe that splits cells
+fn has_pipe(line: &str) -> bool {
+    split_row(line.trim()).len() > 1 || line.trim().starts_with('|')
+}
+
+// Every table of the buffer, outside of fenced code
+pub fn tables(lines: &[String]) -> Vec<Range<usize>> {
+    let fenced = fenced_lines(lines);
+    let mut tables = Vec::new();
+    let mut row = 0;
+
+    while row + 1 < lines.len() {
+        let starts = !fenced[row] && !fenced[row + 1]
+            && has_pipe(&lines[row])
+            && is_delimiter_row(lines[row + 1].trim());
+
+        if !starts {
+            row += 1;
+            continue;
+        }
+
+        let mut end = row + 2;
+        while end < lines.len() && !fenced[end] && has_pipe(&lines[end]) {
+            end += 1;
+        }
+
+        tables.push(row..end);
+        row = end;
+    }
+
+    tables
+}
+
+// Range of the table holding `row`
+pub fn table_at(lines: &[String], row: usize) -> Option<Range<usize>> {
+    tables(lines).into_iter().find(|table| table.contains(&row))
+}
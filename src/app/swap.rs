@@ -0,0 +1,103 @@
+use std::{
+    fs,
+    io,
+    path::{Path, PathBuf},
+};
+use directories::ProjectDirs;
+
+use super::file::write_atomic;
+
+// File names can't be longer than 255 bytes, the temporary file
+// `write_atomic` makes next to the swap file needs some of them
+const MAX_NAME: usize = 200;
+
+// Swap files live in the cache folder, named after the full path of their note
+// with the separators replaced by %, like vim does
+pub fn path(note: &Path) -> Option<PathBuf> {
+    let dirs = ProjectDirs::from("rs", "", "md-tui")?;
+    let note = fs::canonicalize(note).unwrap_or_else(|_| note.to_path_buf());
+
+    Some(dirs.cache_dir().join("swap").join(name(&note)))
+}
+
+// Deep paths keep their end, the note's own name, after a hash of the whole path
+fn name(note: &Path) -> String {
+    let name: String = note.to_string_lossy()
+        .chars()
+        .map(|c| if matches!(c, '/' | '\\' | ':') { '%' } else { c })
+        .collect();
+
+    let name = format!("{name}.swp");
+    if name.len() <= MAX_NAME { return name; }
+
+    let hash = format!("{:016x}%", fnv(name.as_bytes()));
+    let mut start = name.len() - (MAX_NAME - hash.len());
+    while !name.is_char_boundary(start) { start += 1; }
+
+    format!("{hash}{}", &name[start..])
+}
+
+// FNV-1a, the name has to stay the same from one run to the next
+fn fnv(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| (hash ^ *b as u64).wrapping_mul(0x100000001b3))
+}
+
+pub fn write(swap: &Path, lines: &[String]) -> io::Result<()> {
+    if let Some(dir) = swap.parent() { fs::create_dir_all(dir)?; }
+
+    write_atomic(swap, lines.join("\n").as_bytes())
+}
+
+// Split on every newline so the lines come back exactly as written
+pub fn read(swap: &Path) -> Option<Vec<String>> {
+    let content = fs::read(swap).ok()?;
+
+    Some(String::from_utf8_lossy(&content).split('\n').map(String::from).collect())
+}
+
+pub fn remove(swap: &Path) {
+    let _ = fs::remove_file(swap);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deep(dirs: usize, last: &str) -> PathBuf {
+        let mut path = PathBuf::from("/home/user/notes");
+        for i in 0..dirs { path.push(format!("folder-with-a-long-name-{i}")); }
+        path.join(last)
+    }
+
+    #[test]
+    fn short_paths_are_spelled_out() {
+        assert_eq!(name(Path::new("/home/user/notes/todo.md")), "%home%user%notes%todo.md.swp");
+    }
+
+    #[test]
+    fn long_paths_fit_in_a_file_name() {
+        let a = name(&deep(20, "todo.md"));
+        let b = name(&deep(21, "todo.md"));
+
+        assert!(a.len() <= MAX_NAME && b.len() <= MAX_NAME);
+        assert!(a.ends_with("%todo.md.swp"));
+        assert_ne!(a, b);
+        assert_eq!(a, name(&deep(20, "todo.md")));
+
+        // Cut on a char boundary
+        assert!(name(&deep(20, &"é".repeat(150))).len() <= MAX_NAME);
+    }
+
+    #[test]
+    fn a_swap_file_for_a_deep_note_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        let swap = dir.path().join(name(&deep(20, "todo.md")));
+        let lines = vec!["# Todo".to_string(), String::new(), "- one  ".to_string()];
+
+        write(&swap, &lines).unwrap();
+        assert_eq!(read(&swap), Some(lines));
+
+        remove(&swap);
+        assert!(!swap.exists());
+    }
+}
@@ -0,0 +1,5 @@
+// Headless tests: a Model driven by scripted keys and drawn on ratatui's TestBackend
+
+mod harness;
+mod keys;
+mod screens;
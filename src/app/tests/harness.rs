@@ -0,0 +1,203 @@
+use std::{fs, path::PathBuf};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind};
+use ratatui::{backend::TestBackend, style::Style, Terminal};
+use tempfile::TempDir;
+
+use crate::app::{file::File, input, ui, Mode, Model};
+use crate::config::Settings;
+
+pub const WIDTH: u16 = 60;
+pub const HEIGHT: u16 = 16;
+
+// `note.md` opened from a temporary notes folder
+// Keys go through `read_input` and every one of them is followed by a frame, like in the app
+pub struct Harness {
+    pub model: Model,
+    terminal: Terminal<TestBackend>,
+    pub dir: TempDir,
+}
+
+impl Harness {
+    pub fn new(content: &str) -> Harness {
+        Harness::with_settings(content, Settings::default())
+    }
+
+    // The notes folder is always the temporary one,
+    // timers never go off so swap files and disk checks stay out of the way
+    pub fn with_settings(content: &str, settings: Settings) -> Harness {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("note.md");
+        fs::write(&path, content).unwrap();
+
+        let settings = Settings {
+            folder_path: Some(dir.path().to_path_buf()),
+            swap_interval: 0,
+            check_interval: 0,
+            ..settings
+        };
+
+        let model = Model::new(File::load(path), settings);
+        let terminal = Terminal::new(TestBackend::new(WIDTH, HEIGHT)).unwrap();
+
+        let mut harness = Harness { model, terminal, dir };
+        harness.render();
+        harness
+    }
+
+    // Another note in the folder, for the finder
+    pub fn note(&self, name: &str, content: &str) -> PathBuf {
+        let path = self.dir.path().join(name);
+        if let Some(dir) = path.parent() { fs::create_dir_all(dir).unwrap(); }
+
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    // Keys are written like vim mappings, see `parse`
+    pub fn keys(&mut self, keys: &str) -> &mut Harness {
+        for event in parse(keys) {
+            input::read_input(Ok(event), &mut self.model);
+            self.render();
+        }
+
+        self
+    }
+
+    pub fn render(&mut self) {
+        self.terminal.draw(|f| ui::render_frame(&mut self.model, f)).unwrap();
+    }
+
+    // The last frame as text, trailing spaces dropped
+    pub fn screen(&self) -> String {
+        let buffer = self.terminal.backend().buffer();
+
+        (0..buffer.area.height)
+            .map(|y| {
+                let row: String = (0..buffer.area.width).map(|x| buffer.get(x, y).symbol()).collect();
+                row.trim_end().to_string()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    // Style of a cell of the last frame
+    pub fn style(&self, x: u16, y: u16) -> Style {
+        self.terminal.backend().buffer().get(x, y).style()
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        self.model.file.textarea.lines().to_vec()
+    }
+
+    pub fn cursor(&self) -> (usize, usize) {
+        self.model.file.textarea.cursor()
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.model.mode
+    }
+
+    pub fn info(&self) -> Option<&str> {
+        self.model.info.as_deref()
+    }
+
+    // What `note.md` holds on disk
+    pub fn saved(&self) -> String {
+        fs::read_to_string(&self.model.file.path).unwrap()
+    }
+}
+
+// Plain chars stand for themselves, special keys go in brackets:
+// <Esc> <Enter> <Tab> <S-Tab> <BS> <Del> <Up> <Down> <Left> <Right> <Home> <End>
+// <PageUp> <PageDown> <Space> <lt> for `<`, <FocusGained> <FocusLost>,
+// and <ScrollDown> <ScrollUp> for the mouse wheel
+// C-, A- and S- add Ctrl, Alt and Shift: <C-s>, <A-S-r>
+pub fn parse(keys: &str) -> Vec<Event> {
+    let mut events = Vec::new();
+    let mut rest = keys;
+
+    while let Some(c) = rest.chars().next() {
+        if c == '<' {
+            let end = rest.find('>').unwrap_or_else(|| panic!("unclosed key in {keys:?}"));
+            events.push(named(&rest[1..end]));
+            rest = &rest[end + 1..];
+            continue;
+        }
+
+        events.push(press(KeyCode::Char(c), shift(c)));
+        rest = &rest[c.len_utf8()..];
+    }
+
+    events
+}
+
+fn named(name: &str) -> Event {
+    match name {
+        "FocusGained" => return Event::FocusGained,
+        "FocusLost" => return Event::FocusLost,
+        "ScrollDown" => return scroll(MouseEventKind::ScrollDown),
+        "ScrollUp" => return scroll(MouseEventKind::ScrollUp),
+        _ => {},
+    }
+
+    let mut modifiers = KeyModifiers::NONE;
+    let mut key = name;
+
+    while let Some((modifier, rest)) = key.split_once('-').filter(|(_, rest)| !rest.is_empty()) {
+        modifiers |= match modifier {
+            "C" => KeyModifiers::CONTROL,
+            "A" => KeyModifiers::ALT,
+            "S" => KeyModifiers::SHIFT,
+            _ => break,
+        };
+
+        key = rest;
+    }
+
+    let code = match key {
+        "Esc" => KeyCode::Esc,
+        "Enter" => KeyCode::Enter,
+        // Terminals send Shift + Tab as its own key
+        "Tab" if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+        "Tab" => KeyCode::Tab,
+        "BS" => KeyCode::Backspace,
+        "Del" => KeyCode::Delete,
+        "Up" => KeyCode::Up,
+        "Down" => KeyCode::Down,
+        "Left" => KeyCode::Left,
+        "Right" => KeyCode::Right,
+        "Home" => KeyCode::Home,
+        "End" => KeyCode::End,
+        "PageUp" => KeyCode::PageUp,
+        "PageDown" => KeyCode::PageDown,
+        "Space" => KeyCode::Char(' '),
+        "lt" => KeyCode::Char('<'),
+        _ => {
+            let mut chars = key.chars();
+
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => {
+                    modifiers |= shift(c);
+                    KeyCode::Char(c)
+                },
+
+                _ => panic!("unknown key <{name}>"),
+            }
+        },
+    };
+
+    press(code, modifiers)
+}
+
+// Terminals report capitals with Shift held
+fn shift(c: char) -> KeyModifiers {
+    if c.is_uppercase() { KeyModifiers::SHIFT } else { KeyModifiers::NONE }
+}
+
+fn press(code: KeyCode, modifiers: KeyModifiers) -> Event {
+    Event::Key(KeyEvent::new(code, modifiers))
+}
+
+fn scroll(kind: MouseEventKind) -> Event {
+    Event::Mouse(MouseEvent { kind, column: 0, row: 0, modifiers: KeyModifiers::NONE })
+}
@@ -0,0 +1,775 @@
+// One test per binding, or a few that only make sense together
+
+use std::fs;
+use super::harness::Harness;
+use crate::app::{keymap::Keymap, vim, Mode};
+use crate::config::Settings;
+use ratatui::style::Modifier;
+
+const NOTE: &str = "# Title\n\nFirst paragraph.\n\n- one\n- two\n";
+
+const TABLE: &str = "| a | b |\n| --- | --- |\n| 1 | 2 |\n";
+
+fn long_note() -> String {
+    (1..=40).map(|i| format!("Line {i}\n\n")).collect()
+}
+
+#[test]
+fn opens_on_the_preview() {
+    let h = Harness::new(NOTE);
+
+    assert_eq!(h.mode(), Mode::View);
+    assert!(h.screen().contains("[VIEW]"));
+}
+
+#[test]
+fn i_and_enter_start_editing() {
+    assert_eq!(Harness::new(NOTE).keys("i").mode(), Mode::Edit);
+    assert_eq!(Harness::new(NOTE).keys("<Enter>").mode(), Mode::Edit);
+    assert!(Harness::new(NOTE).keys("i").screen().contains("[EDIT]"));
+}
+
+#[test]
+fn esc_stops_editing() {
+    assert_eq!(Harness::new(NOTE).keys("i<Esc>").mode(), Mode::View);
+}
+
+#[test]
+fn typing_edits_the_note() {
+    let mut h = Harness::new(NOTE);
+    h.keys("iHi <Esc>");
+
+    assert_eq!(h.lines()[0], "Hi # Title");
+    assert_eq!(h.cursor(), (0, 3));
+    assert!(h.screen().contains("[note] [+]"));
+}
+
+#[test]
+fn ctrl_s_saves() {
+    let mut h = Harness::new(NOTE);
+    h.keys("iHi <C-s>");
+
+    assert_eq!(h.mode(), Mode::View);
+    assert_eq!(h.info(), Some("File has been saved!"));
+    assert_eq!(h.saved(), format!("Hi {NOTE}"));
+    assert!(!h.screen().contains("[+]"));
+}
+
+#[test]
+fn q_and_esc_exit() {
+    assert_eq!(Harness::new(NOTE).keys("q").mode(), Mode::Exit);
+    assert_eq!(Harness::new(NOTE).keys("<Esc>").mode(), Mode::Exit);
+    assert_eq!(Harness::new(NOTE).keys("<Tab>q").mode(), Mode::Exit);
+}
+
+#[test]
+fn exiting_with_unsaved_changes_asks_first() {
+    let mut h = Harness::new(NOTE);
+    h.keys("iHi <Esc>q");
+
+    assert_eq!(h.mode(), Mode::Save);
+    assert!(h.screen().contains("Save changes to [note]?"));
+
+    h.keys("c");
+    assert_eq!(h.mode(), Mode::View);
+
+    h.keys("qd");
+    assert_eq!(h.mode(), Mode::Exit);
+    assert_eq!(h.saved(), NOTE);
+}
+
+#[test]
+fn s_in_the_prompt_saves_then_exits() {
+    let mut h = Harness::new(NOTE);
+    h.keys("iHi <Esc>qs");
+
+    assert_eq!(h.mode(), Mode::Exit);
+    assert_eq!(h.saved(), format!("Hi {NOTE}"));
+}
+
+#[test]
+fn v_in_the_prompt_shows_the_diff() {
+    let mut h = Harness::new(NOTE);
+    h.keys("iHi <Esc>qv");
+
+    assert_eq!(h.mode(), Mode::Save);
+    assert!(h.screen().contains("- # Title"));
+    assert!(h.screen().contains("+ Hi # Title"));
+
+    h.keys("v");
+    assert!(!h.screen().contains("- # Title"));
+}
+
+#[test]
+fn tab_toggles_the_source() {
+    let mut h = Harness::new(NOTE);
+
+    h.keys("<Tab>");
+    assert!(h.screen().contains("[SOURCE]"));
+    assert!(h.screen().contains("# Title"));
+
+    h.keys("<Tab>");
+    assert!(h.screen().contains("[VIEW]"));
+}
+
+#[test]
+fn hjkl_and_arrows_move_in_the_source() {
+    let mut h = Harness::new(NOTE);
+
+    h.keys("<Tab>jjll");
+    assert_eq!(h.cursor(), (2, 2));
+
+    h.keys("hk");
+    assert_eq!(h.cursor(), (1, 0));
+
+    // Left at the start of a line goes to the end of the one above
+    h.keys("h");
+    assert_eq!(h.cursor(), (0, 7));
+
+    h.keys("<Down><Down><Right><Left><Up>");
+    assert_eq!(h.cursor(), (1, 0));
+}
+
+#[test]
+fn shift_j_and_k_go_to_the_end_and_start() {
+    let mut h = Harness::new(NOTE);
+
+    h.keys("<Tab>J");
+    assert_eq!(h.cursor().0, 5);
+
+    h.keys("K");
+    assert_eq!(h.cursor().0, 0);
+}
+
+#[test]
+fn j_k_and_pages_scroll_the_preview() {
+    let mut h = Harness::new(&long_note());
+
+    h.keys("jj");
+    assert_eq!(h.model.preview.scroll, 2);
+
+    h.keys("k");
+    assert_eq!(h.model.preview.scroll, 1);
+
+    let page = h.model.preview.height;
+    h.keys("<PageDown>");
+    assert_eq!(h.model.preview.scroll, 1 + page);
+
+    h.keys("<PageUp>");
+    assert_eq!(h.model.preview.scroll, 1);
+
+    h.keys("J");
+    assert_eq!(h.model.preview.scroll, h.model.preview.max_scroll());
+
+    h.keys("K");
+    assert_eq!(h.model.preview.scroll, 0);
+}
+
+#[test]
+fn ctrl_t_toggles_the_side_by_side_preview() {
+    let mut h = Harness::new(NOTE);
+
+    h.keys("<C-t>");
+    assert!(h.screen().contains("[PREVIEW]"));
+
+    h.keys("i<C-t>");
+    assert!(!h.screen().contains("[PREVIEW]"));
+}
+
+#[test]
+fn alt_z_toggles_soft_wrap() {
+    let mut h = Harness::new(&format!("{}\n", "word ".repeat(30)));
+
+    h.keys("<Tab>");
+    assert!(!h.model.wrap);
+    assert!(!h.screen().lines().nth(3).unwrap().contains("word"));
+
+    h.keys("<A-z>");
+    assert!(h.model.wrap);
+    assert!(h.screen().lines().nth(3).unwrap().contains("word"));
+}
+
+#[test]
+fn alt_q_reflows_the_paragraph() {
+    let settings = Settings { reflow_width: 20, ..Settings::default() };
+    let mut h = Harness::with_settings(&format!("{}\n", "word ".repeat(10).trim()), settings);
+
+    h.keys("i<A-q>");
+
+    assert_eq!(h.lines(), ["word word word word", "word word word word", "word word"]);
+}
+
+#[test]
+fn ctrl_z_undoes_and_ctrl_y_redoes() {
+    let mut h = Harness::new(NOTE);
+
+    h.keys("iX<C-z>");
+    assert_eq!(h.lines()[0], "# Title");
+
+    h.keys("<C-y>");
+    assert_eq!(h.lines()[0], "X# Title");
+}
+
+#[test]
+fn tab_and_shift_tab_move_between_cells() {
+    let mut h = Harness::new(TABLE);
+
+    // The table is aligned on the way
+    h.keys("i<Tab>");
+    assert_eq!(h.lines()[0], "| a   | b   |");
+    assert_eq!(h.cursor(), (0, 8));
+
+    h.keys("<S-Tab>");
+    assert_eq!(h.cursor(), (0, 2));
+}
+
+#[test]
+fn enter_in_a_table_goes_to_the_cell_below() {
+    let mut h = Harness::new(TABLE);
+
+    h.keys("i<Enter>");
+    assert_eq!(h.cursor(), (2, 2));
+
+    // A new row past the last one
+    h.keys("<Enter>");
+    assert_eq!(h.lines().len(), 4);
+    assert_eq!(h.cursor().0, 3);
+}
+
+#[test]
+fn alt_r_inserts_and_deletes_rows() {
+    let mut h = Harness::new(TABLE);
+
+    h.keys("i<Down><Down><A-r>");
+    assert_eq!(h.lines(), ["| a   | b   |", "| --- | --- |", "| 1   | 2   |", "|     |     |"]);
+    assert_eq!(h.cursor().0, 3);
+
+    h.keys("<A-R>");
+    assert_eq!(h.lines(), ["| a   | b   |", "| --- | --- |", "| 1   | 2   |"]);
+}
+
+#[test]
+fn alt_c_inserts_and_deletes_columns() {
+    let mut h = Harness::new(TABLE);
+
+    h.keys("i<A-c>");
+    assert_eq!(h.lines()[0], "| a   |     | b   |");
+
+    h.keys("<A-C>");
+    assert_eq!(h.lines()[0], "| a   | b   |");
+}
+
+#[test]
+fn alt_a_cycles_the_column_alignment() {
+    let mut h = Harness::new(TABLE);
+
+    h.keys("i<A-a>");
+    assert_eq!(h.lines()[1], "| :-- | --- |");
+}
+
+#[test]
+fn ctrl_p_finds_and_opens_a_note() {
+    let mut h = Harness::new(NOTE);
+    h.note("ideas/other.md", "# Other\n");
+
+    h.keys("<C-p>");
+    assert_eq!(h.mode(), Mode::Find);
+    assert!(h.screen().contains("[FIND]"));
+
+    h.keys("oth");
+    assert_eq!(h.model.finder.query, "oth");
+    assert_eq!(h.model.finder.hits.len(), 1);
+
+    h.keys("<Enter>");
+    assert_eq!(h.mode(), Mode::View);
+    assert_eq!(h.model.file.name, "other");
+}
+
+#[test]
+fn ctrl_p_tells_when_the_notes_cant_be_listed() {
+    let mut h = Harness::new(NOTE);
+    h.model.settings.folder_path = Some(h.dir.path().join("missing"));
+
+    h.keys("<C-p>");
+    assert_eq!(h.mode(), Mode::View);
+    assert!(h.info().is_some_and(|info| info.starts_with("Notes not listed: ")));
+}
+
+#[test]
+fn finder_keys_edit_the_query_and_selection() {
+    let mut h = Harness::new(NOTE);
+    h.note("a.md", "");
+    h.note("b.md", "");
+
+    h.keys("<C-p>ab<BS>");
+    assert_eq!(h.model.finder.query, "a");
+
+    h.keys("<C-u>");
+    assert_eq!(h.model.finder.query, "");
+
+    h.keys("<C-j><Down>");
+    assert_eq!(h.model.finder.selected, 2);
+
+    h.keys("<C-k>");
+    assert_eq!(h.model.finder.selected, 1);
+
+    h.keys("<Esc>");
+    assert_eq!(h.mode(), Mode::View);
+}
+
+#[test]
+fn saving_over_an_outside_change_asks_first() {
+    let mut h = Harness::new(NOTE);
+    fs::write(&h.model.file.path, "Changed elsewhere\n").unwrap();
+
+    h.keys("iHi <C-s>");
+    assert_eq!(h.mode(), Mode::Save);
+    assert!(h.screen().contains("[note] was changed by another program"));
+
+    h.keys("v");
+    assert!(h.screen().contains("- Changed elsewhere"));
+
+    h.keys("o");
+    assert_eq!(h.mode(), Mode::View);
+    assert_eq!(h.saved(), format!("Hi {NOTE}"));
+}
+
+#[test]
+fn r_reloads_the_outside_change() {
+    let mut h = Harness::new(NOTE);
+    fs::write(&h.model.file.path, "Changed elsewhere\n").unwrap();
+
+    h.keys("iHi <C-s>r");
+
+    assert_eq!(h.lines(), ["Changed elsewhere"]);
+    assert!(!h.model.file.is_dirty());
+}
+
+#[test]
+fn losing_focus_dims_and_autosaves() {
+    let settings = Settings { autosave: true, ..Settings::default() };
+    let mut h = Harness::with_settings(NOTE, settings);
+
+    h.keys("iHi <FocusLost>");
+    assert!(!h.model.is_focused);
+    assert_eq!(h.saved(), format!("Hi {NOTE}"));
+
+    h.keys("<FocusGained>");
+    assert!(h.model.is_focused);
+}
+
+#[test]
+fn the_config_file_rebinds_keys() {
+    let mut settings = Settings::default();
+    settings.keymap.preview.insert("e".into(), "edit".into());
+    settings.keymap.preview.insert("i".into(), "none".into());
+    settings.keymap.edit.insert("<C-q>".into(), "view".into());
+
+    let mut h = Harness::with_settings(NOTE, settings);
+
+    assert_eq!(h.keys("i").mode(), Mode::View);
+    assert_eq!(h.keys("e").mode(), Mode::Edit);
+    assert_eq!(h.keys("<C-q>").mode(), Mode::View);
+}
+
+#[test]
+fn keymap_mistakes_are_reported() {
+    let mut settings = Settings::default();
+    settings.keymap.preview.insert("x".into(), "explode".into());
+    settings.keymap.preview.insert("<C-s>".into(), "undo".into());
+    settings.keymap.source.insert("<Tab>".into(), "quit".into());
+    settings.keymap.source.insert("<S-Tab>".into(), "edit".into());
+    settings.keymap.source.insert("<lt>".into(), "top".into());
+    settings.keymap.source.insert("<C-x".into(), "top".into());
+    settings.keymap.edit.insert("x".into(), "save".into());
+    settings.keymap.find.insert("<C-k>".into(), "down".into());
+
+    let errors = Keymap::new(&settings.keymap).unwrap_err();
+
+    assert_eq!(errors, [
+        "[keymap.preview]: 'undo' can't be used in this mode, expected one of: none, quit, edit, save, find, \
+         split, source, top, bottom, up, down, page-up, page-down",
+        "[keymap.preview]: 'explode' is an unknown action in this mode, expected one of: none, quit, edit, save, \
+         find, split, source, top, bottom, up, down, page-up, page-down",
+        "[keymap.source]: '<C-x' isn't a key, write keys like `q`, `<Esc>` or `<C-s>`",
+        "[keymap.edit]: 'x' types text in this mode, add Ctrl or Alt to bind it",
+    ]);
+}
+
+#[test]
+fn the_same_key_twice_is_a_conflict() {
+    let mut settings = Settings::default();
+    settings.keymap.preview.insert("R".into(), "top".into());
+    settings.keymap.preview.insert("<S-r>".into(), "bottom".into());
+
+    let errors = Keymap::new(&settings.keymap).unwrap_err();
+    assert_eq!(errors, ["[keymap.preview]: 'R' and '<S-r>' are the same key"]);
+}
+
+const PROSE: &str = "one two three\nsay \"hi there\" now\n\nsecond para\nstill second\n";
+
+#[test]
+fn counts_and_motions_move_the_source_cursor() {
+    let mut h = Harness::new(PROSE);
+    h.keys("<Tab>");
+
+    assert_eq!(h.keys("w").cursor(), (0, 4));
+    assert_eq!(h.keys("e").cursor(), (0, 6));
+    assert_eq!(h.keys("2b").cursor(), (0, 0));
+    assert_eq!(h.keys("$").cursor(), (0, 12));
+    assert_eq!(h.keys("0").cursor(), (0, 0));
+    assert_eq!(h.keys("3j").cursor(), (3, 0));
+    assert_eq!(h.keys("G").cursor(), (4, 0));
+    assert_eq!(h.keys("gg").cursor(), (0, 0));
+    assert_eq!(h.keys("2G").cursor(), (1, 0));
+    assert_eq!(h.keys("}").cursor(), (2, 0));
+    assert_eq!(h.keys("{").cursor(), (0, 0));
+    assert_eq!(h.keys("10l").cursor(), (0, 10));
+}
+
+#[test]
+fn operators_take_motions_and_counts() {
+    let mut h = Harness::new(PROSE);
+    h.keys("<Tab>dw");
+    assert_eq!(h.lines()[0], "two three");
+
+    h.keys("d$");
+    assert_eq!(h.lines()[0], "");
+
+    let mut h = Harness::new(PROSE);
+    h.keys("<Tab>2dw");
+    assert_eq!(h.lines()[0], "three");
+
+    let mut h = Harness::new(PROSE);
+    h.keys("<Tab>d2e");
+    assert_eq!(h.lines()[0], " three");
+
+    let mut h = Harness::new(PROSE);
+    h.keys("<Tab>2dd");
+    assert_eq!(h.lines(), ["", "second para", "still second"]);
+
+    let mut h = Harness::new(PROSE);
+    h.keys("<Tab>Gdk");
+    assert_eq!(h.lines(), ["one two three", "say \"hi there\" now", ""]);
+}
+
+#[test]
+fn operators_take_text_objects() {
+    let mut h = Harness::new(PROSE);
+    h.keys("<Tab>wdiw");
+    assert_eq!(h.lines()[0], "one  three");
+
+    let mut h = Harness::new(PROSE);
+    h.keys("<Tab>wdaw");
+    assert_eq!(h.lines()[0], "one three");
+
+    let mut h = Harness::new(PROSE);
+    h.keys("<Tab>jdi\"");
+    assert_eq!(h.lines()[1], "say \"\" now");
+
+    let mut h = Harness::new(PROSE);
+    h.keys("<Tab>jda\"");
+    assert_eq!(h.lines()[1], "say now");
+
+    let mut h = Harness::new(PROSE);
+    h.keys("<Tab>Gdap");
+    assert_eq!(h.lines(), ["one two three", "say \"hi there\" now"]);
+
+    let mut h = Harness::new(PROSE);
+    h.keys("<Tab>dip");
+    assert_eq!(h.lines(), ["", "second para", "still second"]);
+}
+
+#[test]
+fn change_types_over_and_undoes_as_one() {
+    let mut h = Harness::new(PROSE);
+    h.keys("<Tab>cwuno<Esc>");
+
+    assert_eq!(h.mode(), Mode::View);
+    assert_eq!(h.lines()[0], "uno two three");
+
+    h.keys("u");
+    assert_eq!(h.lines()[0], "one two three");
+
+    h.keys("<C-r>");
+    assert_eq!(h.lines()[0], "uno two three");
+}
+
+#[test]
+fn dot_repeats_the_last_change() {
+    let mut h = Harness::new(PROSE);
+    h.keys("<Tab>cwuno<Esc>w.");
+    assert_eq!(h.lines()[0], "uno uno three");
+
+    let mut h = Harness::new(PROSE);
+    h.keys("<Tab>dw.");
+    assert_eq!(h.lines()[0], "three");
+
+    let mut h = Harness::new(PROSE);
+    h.keys("<Tab>x3.");
+    assert_eq!(h.lines()[0], "two three");
+}
+
+#[test]
+fn yank_and_put() {
+    let mut h = Harness::new(PROSE);
+    h.keys("<Tab>yyjp");
+    assert_eq!(h.lines()[2], "one two three");
+    assert_eq!(h.cursor(), (2, 0));
+
+    let mut h = Harness::new(PROSE);
+    h.keys("<Tab>yw$p");
+    assert_eq!(h.lines()[0], "one two threeone ");
+
+    let mut h = Harness::new(PROSE);
+    h.keys("<Tab>ddP");
+    assert_eq!(h.lines()[0], "one two three");
+    assert_eq!(h.lines()[1], "say \"hi there\" now");
+}
+
+#[test]
+fn shift_operators_indent_lines() {
+    let mut h = Harness::new(PROSE);
+    h.keys("<Tab>>j");
+    assert_eq!(h.lines()[..2], ["    one two three", "    say \"hi there\" now"]);
+
+    h.keys("<lt><lt>");
+    assert_eq!(h.lines()[0], "one two three");
+
+    h.keys("u");
+    assert_eq!(h.lines()[0], "    one two three");
+}
+
+#[test]
+fn half_typed_commands_show_and_cancel() {
+    let mut h = Harness::new(PROSE);
+    h.keys("<Tab>2d");
+    assert!(h.screen().contains("[SOURCE] 2d"));
+
+    h.keys("<Esc>");
+    assert_eq!(h.mode(), Mode::View);
+    assert!(!h.screen().contains("2d"));
+
+    h.keys("j");
+    assert_eq!(h.lines().len(), 5);
+    assert_eq!(h.cursor(), (1, 0));
+}
+
+#[test]
+fn v_selects_chars_for_an_operator() {
+    let mut h = Harness::new(PROSE);
+    h.keys("<Tab>wve");
+
+    assert_eq!(h.mode(), Mode::Visual);
+    assert!(h.screen().contains("[VISUAL]"));
+
+    h.keys("d");
+    assert_eq!(h.mode(), Mode::View);
+    assert_eq!(h.lines()[0], "one  three");
+
+    h.keys("P");
+    assert_eq!(h.lines()[0], "one two three");
+}
+
+#[test]
+fn shift_v_selects_lines() {
+    let mut h = Harness::new(PROSE);
+    h.keys("<Tab>Vj");
+
+    assert_eq!(h.mode(), Mode::VisualLine);
+    assert!(h.screen().contains("[VISUAL LINE]"));
+
+    h.keys("y");
+    assert_eq!(h.cursor(), (0, 0));
+
+    h.keys("Gp");
+    assert_eq!(h.lines()[5..], ["one two three", "say \"hi there\" now"]);
+
+    h.keys("Vk2>");
+    assert_eq!(h.lines()[4], "        still second");
+    assert_eq!(h.lines()[5], "        one two three");
+    assert_eq!(h.lines()[6], "say \"hi there\" now");
+}
+
+#[test]
+fn visual_star_wraps_in_emphasis() {
+    let mut h = Harness::new(PROSE);
+    h.keys("<Tab>wviw*");
+    assert_eq!(h.lines()[0], "one *two* three");
+
+    let mut h = Harness::new(PROSE);
+    h.keys("<Tab>V*");
+    assert_eq!(h.lines()[0], "*one two three*");
+
+    h.keys("u");
+    assert_eq!(h.lines()[0], "one two three");
+}
+
+#[test]
+fn huge_counts_stop_at_the_end_of_the_note() {
+    let mut h = Harness::new(PROSE);
+    h.keys("<Tab>");
+
+    assert_eq!(h.keys("99999999999999999999j").cursor(), (4, 0));
+    assert_eq!(h.keys("99999999999999999999k").cursor(), (0, 0));
+    assert_eq!(h.keys("99999999999999999999e").cursor(), (4, 11));
+    assert_eq!(h.keys("99999999999999999999b").cursor(), (0, 0));
+    assert_eq!(h.keys("99999999999999999999}").cursor(), (4, 12));
+    assert_eq!(h.keys("99999999999999999999$").cursor(), (4, 11));
+
+    h.keys("gg99999999999999999999dd");
+    assert_eq!(h.lines(), [""]);
+}
+
+#[test]
+fn huge_counts_put_and_indent_a_bounded_number_of_times() {
+    let mut h = Harness::new(PROSE);
+    h.keys("<Tab>yl99999999999999999999p");
+    assert_eq!(h.lines()[0].len(), "one two three".len() + vim::MAX_REPEAT);
+
+    h.keys("j99999999999999999999>>V99999999999999999999>");
+    let indent = h.model.file.textarea.indent().len();
+    assert_eq!(h.lines()[1].len() - h.lines()[1].trim_start().len(), indent * (1 + vim::MAX_REPEAT));
+}
+
+#[test]
+fn gq_reflows_the_lines_it_covers() {
+    let settings = Settings { reflow_width: 8, ..Settings::default() };
+    let text = "a b c d e f\ng h\ni j k l m n\n";
+
+    let mut h = Harness::with_settings(text, settings.clone());
+    h.keys("<Tab>Vjgq");
+    assert_eq!(h.lines(), ["a b c d", "e f g h", "i j k l m n"]);
+
+    let mut h = Harness::with_settings(text, settings.clone());
+    h.keys("<Tab>gqq");
+    assert_eq!(h.lines(), ["a b c d", "e f", "g h", "i j k l m n"]);
+
+    let mut h = Harness::with_settings(text, settings);
+    h.keys("<Tab>jgqip");
+    assert_eq!(h.lines(), ["a b c d", "e f g h", "i j k l", "m n"]);
+}
+
+#[test]
+fn visual_ip_selects_the_paragraph_lines() {
+    let mut h = Harness::new(PROSE);
+    h.keys("<Tab>Gvip");
+    assert_eq!(h.mode(), Mode::VisualLine);
+
+    h.keys("d");
+    assert_eq!(h.lines(), ["one two three", "say \"hi there\" now", ""]);
+}
+
+#[test]
+fn visual_motions_extend_from_either_end() {
+    let mut h = Harness::new(PROSE);
+    h.keys("<Tab>wvlloh");
+
+    assert_eq!(h.cursor(), (0, 3));
+    h.keys("c1<Esc>");
+    assert_eq!(h.lines()[0], "one1 three");
+
+    let mut h = Harness::new(PROSE);
+    h.keys("<Tab>vVv<Esc>");
+    assert_eq!(h.mode(), Mode::View);
+    assert_eq!(h.lines().len(), 5);
+}
+
+#[test]
+fn visual_mode_takes_the_source_keymap_for_other_keys() {
+    let mut settings = Settings { reflow_width: 8, ..Settings::default() };
+    settings.keymap.source.insert("<C-c>".into(), "quit".into());
+    settings.keymap.source.insert("<C-n>".into(), "down".into());
+
+    let mut h = Harness::with_settings(PROSE, settings);
+    h.keys("<Tab>V<C-n>");
+    assert_eq!((h.mode(), h.cursor()), (Mode::VisualLine, (1, 0)));
+
+    h.keys("<C-c>");
+    assert_eq!(h.mode(), Mode::View);
+
+    h.keys("ggV<A-q>");
+    assert_eq!(h.mode(), Mode::View);
+    assert_eq!(h.lines()[..3], ["one two", "three", "say \"hi there\" now"]);
+}
+
+#[test]
+fn bound_letters_work_in_visual_mode() {
+    let mut h = Harness::new(PROSE);
+    h.keys("<Tab>jVK");
+    assert_eq!((h.mode(), h.cursor().0), (Mode::VisualLine, 0));
+
+    h.keys("J");
+    assert_eq!((h.mode(), h.cursor().0), (Mode::VisualLine, 4));
+
+    let mut settings = Settings::default();
+    settings.keymap.source.insert("Q".into(), "bottom".into());
+
+    let mut h = Harness::with_settings(PROSE, settings);
+    h.keys("<Tab>vQd");
+    assert_eq!(h.lines(), ["till second"]);
+
+    // `i` enters Edit mode in the source, here it picks a text object
+    h.keys("viw");
+    assert_eq!(h.mode(), Mode::Visual);
+}
+
+#[test]
+fn empty_lines_in_the_selection_show_a_selected_cell() {
+    let mut h = Harness::new(PROSE);
+    h.keys("<Tab>Vjjj");
+
+    let screen = h.screen();
+    let (y, line) = screen.lines().enumerate().find(|(_, line)| line.contains("say")).unwrap();
+    let x = line[..line.find("say").unwrap()].chars().count() as u16;
+
+    assert!(h.style(x, y as u16 + 1).add_modifier.contains(Modifier::REVERSED));
+
+    h.keys("<Esc>");
+    assert!(!h.style(x, y as u16 + 1).add_modifier.contains(Modifier::REVERSED));
+}
+
+#[test]
+fn pages_scroll_the_source_and_extend_a_selection() {
+    let mut h = Harness::new(&long_note());
+    h.keys("<Tab>");
+
+    let page = h.model.viewport.height;
+    h.keys("<PageDown>");
+    assert_eq!(h.model.viewport.row, page);
+
+    h.keys("<PageUp>V<PageDown>");
+    assert_eq!(h.mode(), Mode::VisualLine);
+    assert_eq!(h.model.viewport.row, page);
+    assert!(h.cursor().0 >= page);
+}
+
+#[test]
+fn scrolling_while_editing_keeps_the_cursor_in_view() {
+    let mut h = Harness::new(&long_note());
+    h.keys("i");
+    for _ in 0..30 { h.keys("<Down>"); }
+    h.keys("<Right><Right><Right>");
+
+    let at = h.cursor();
+    assert_eq!(at.1, 3);
+
+    // The textarea on its own would jump to the top
+    h.keys("<ScrollDown>");
+    assert_eq!(h.cursor(), at);
+
+    h.keys("<ScrollUp><ScrollUp>");
+    assert!(h.cursor().0 + 2 >= at.0);
+
+    let (top, page) = (h.model.viewport.row, h.model.viewport.height);
+    h.keys("<PageUp>");
+    assert_eq!(h.model.viewport.row, top - page);
+
+    h.keys("<C-v>");
+    assert_eq!(h.model.viewport.row, top);
+
+    h.keys("<A-v><PageDown>");
+    assert_eq!(h.model.viewport.row, top);
+    assert_eq!(h.mode(), Mode::Edit);
+    assert!(!h.model.file.is_dirty());
+}
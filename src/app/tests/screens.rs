@@ -0,0 +1,55 @@
+// Whole screens compared against the snapshots next to this file
+// Run with INSTA_UPDATE=always (or `cargo insta review`) after changing how things look
+
+use std::fs;
+use insta::assert_snapshot;
+use super::harness::Harness;
+
+const NOTE: &str = "\
+# Title
+
+Some *emphasis* and `code`.
+
+- one
+- two
+
+| a | b |
+| --- | --- |
+| 1 | 2 |
+";
+
+#[test]
+fn preview() {
+    assert_snapshot!(Harness::new(NOTE).screen());
+}
+
+#[test]
+fn source() {
+    assert_snapshot!(Harness::new(NOTE).keys("<Tab>jj").screen());
+}
+
+#[test]
+fn edit_side_by_side() {
+    assert_snapshot!(Harness::new(NOTE).keys("i<C-t>").screen());
+}
+
+#[test]
+fn finder() {
+    let mut h = Harness::new(NOTE);
+    h.note("ideas/other.md", "# Other\n\nAnother note.\n");
+
+    assert_snapshot!(h.keys("<C-p>oth").screen());
+}
+
+#[test]
+fn unsaved_prompt() {
+    assert_snapshot!(Harness::new(NOTE).keys("iNew <Esc>q").screen());
+}
+
+#[test]
+fn changed_on_disk_diff() {
+    let mut h = Harness::new(NOTE);
+    fs::write(&h.model.file.path, NOTE.replace("- two", "- three")).unwrap();
+
+    assert_snapshot!(h.keys("iNew <C-s>v").screen());
+}
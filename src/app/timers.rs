@@ -0,0 +1,55 @@
+use std::time::{Duration, Instant};
+
+// Things that happen on their own after a while
+// Each one turns into a message when it goes off
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timer {
+    // Look at the note on disk
+    Check,
+    // Copy unsaved changes to the swap file
+    Swap,
+    // Save after a while without input
+    Autosave,
+    // Hide the message under the note
+    Info,
+}
+
+// When each timer goes off next, a timer is set once at most
+#[derive(Debug, Default)]
+pub struct Timers {
+    pending: Vec<(Timer, Instant)>,
+}
+
+impl Timers {
+    // Replaces the time it was set to before
+    pub fn set(&mut self, timer: Timer, after: Duration) {
+        self.cancel(timer);
+        self.pending.push((timer, Instant::now() + after));
+    }
+
+    pub fn cancel(&mut self, timer: Timer) {
+        self.pending.retain(|(pending, _)| *pending != timer);
+    }
+
+    // Time left until the next one goes off
+    pub fn next_in(&self) -> Option<Duration> {
+        self.pending.iter()
+            .map(|(_, at)| at.saturating_duration_since(Instant::now()))
+            .min()
+    }
+
+    // The ones that went off, earliest first, they have to be set again to repeat
+    pub fn due(&mut self) -> Vec<Timer> {
+        let now = Instant::now();
+
+        let mut due: Vec<(Timer, Instant)> = self.pending.iter()
+            .filter(|(_, at)| *at <= now)
+            .copied()
+            .collect();
+
+        self.pending.retain(|(_, at)| *at > now);
+        due.sort_by_key(|(_, at)| *at);
+
+        due.into_iter().map(|(timer, _)| timer).collect()
+    }
+}
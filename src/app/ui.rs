@@ -1,44 +1,219 @@
-use super::{Model, Mode};
+use super::{Model, Mode, Prompt};
+use super::editor::Editor;
+use super::finder::FinderPopup;
+use super::markdown::preview::PreviewPane;
 
 use ratatui::{prelude::*, widgets::*};
 use ratatui::terminal::Frame;
 
 pub fn render_frame(model: &mut Model, f: &mut Frame) {
+    render_note(model, f);
+
+    // The finder floats over the note it was opened from
+    if model.mode == Mode::Find {
+        let style = if model.is_focused {
+            Style::default()
+        } else {
+            Style::new().add_modifier(Modifier::DIM)
+        };
+
+        f.render_stateful_widget(FinderPopup::default().style(style), f.size(), &mut model.finder);
+    }
+
+    if model.mode == Mode::Save {
+        render_prompt(model, f);
+    }
+}
+
+// Asks what to do with unsaved changes, a note changed on disk or a swap file left behind,
+// the diff against the disk shows above the question when asked for
+fn render_prompt(model: &mut Model, f: &mut Frame) {
+    let (title, question, answers) = match &model.prompt {
+        Some((Prompt::Changed(_), _)) => (
+            "[CHANGED]",
+            format!("[{}] was changed by another program", model.file.name),
+            answers(&["reload", "overwrite", "view diff", "cancel"]),
+        ),
+
+        Some((Prompt::Recover(_), _)) => (
+            "[RECOVER]",
+            format!("[{}] has unsaved changes from a session that didn't exit", model.file.name),
+            answers(&["recover", "discard", "view diff", "cancel"]),
+        ),
+
+        _ => (
+            "[UNSAVED]",
+            format!("Save changes to [{}]?", model.file.name),
+            answers(&["save", "discard", "view diff", "cancel"]),
+        ),
+    };
+
+    let question = Line::from(question);
+
+    let area = if model.diff.is_some() {
+        // As large as the finder
+        let [_, area, _] = Layout::vertical([
+            Constraint::Percentage(10),
+            Constraint::Percentage(80),
+            Constraint::Percentage(10),
+        ]).areas(f.size());
+
+        let [_, area, _] = Layout::horizontal([
+            Constraint::Percentage(5),
+            Constraint::Percentage(90),
+            Constraint::Percentage(5),
+        ]).areas(area);
+
+        area
+    } else {
+        let width = (question.width().max(answers.width()) + 4) as u16;
+
+        let [_, area, _] = Layout::vertical([Constraint::Fill(1), Constraint::Length(5), Constraint::Fill(1)])
+            .areas(f.size());
+        let [_, area, _] = Layout::horizontal([Constraint::Fill(1), Constraint::Length(width), Constraint::Fill(1)])
+            .areas(area);
+
+        area
+    };
+
+    let mut block = Block::new()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .padding(Padding::horizontal(1))
+                .title_top(Line::from(title).alignment(Alignment::Center));
+
+    if model.diff.is_some() {
+        let legend = match &model.prompt {
+            Some((Prompt::Recover(_), _)) => "[- on disk, + recovered]",
+            _ => "[- on disk, + in the editor]",
+        };
+
+        block = block.title_bottom(Line::from(legend).alignment(Alignment::Right));
+    }
+
+    let inner = block.inner(area);
+
+    f.render_widget(Clear, area);
+    f.render_widget(block, area);
+
+    let [diff_area, question_area] = Layout::vertical([
+        Constraint::Fill(1),
+        Constraint::Length(3),
+    ]).areas(inner);
+
+    if let Some(diff) = &mut model.diff {
+        diff.height = diff_area.height as usize;
+        diff.scroll(0);
+
+        f.render_widget(
+            Paragraph::new(diff.lines.clone()).scroll((diff.scroll as u16, 0)),
+            diff_area
+        );
+    }
+
+    f.render_widget(
+        Paragraph::new(vec![question, Line::default(), answers]).alignment(Alignment::Center),
+        question_area
+    );
+}
+
+// Answers with the key that picks them underlined
+fn answers(words: &[&str]) -> Line<'static> {
+    let key = Style::new().add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+    let mut spans = Vec::new();
+
+    for (i, word) in words.iter().enumerate() {
+        let (first, rest) = word.split_at(1);
+
+        // "view diff" is picked with v
+        spans.push(Span::styled(first.to_string(), key));
+        spans.push(Span::raw(rest.to_string()));
+
+        if i + 1 < words.len() { spans.push(Span::raw("   ")); }
+    }
+
+    Line::from(spans)
+}
+
+fn render_note(model: &mut Model, f: &mut Frame) {
 	// 
-	/*
     let info_space = match &model.info {
         Some(_) => 1,
         None => 0,
     };
-	*/
+
     // Define layout parts
     let layout = Layout::default()
                     .direction(Direction::Vertical)
                     .constraints(
                         [
                             Constraint::Fill(1),
-                            //Constraint::Length(info_space)
+                            Constraint::Length(info_space)
                         ]
                     );
 
     let chunks = layout.split(f.size());        
 
+    //
+    if let Some(info) = &model.info {
+        let info_block = Block::new()
+                            .borders(Borders::NONE)
+                            .title(info.as_str())
+                            .title_alignment(Alignment::Center);
+
+        f.render_widget(
+            info_block,
+            chunks[1]
+        );
+    }
+
+    // Behind the finder and the prompt the note stays as it was
+    let note_mode = model.note_mode();
+
     // 
-    let file_name = Line::from(format!("[{}]", model.file.name))
+    let read_only = if model.file.read_only.is_some() { " [READ-ONLY]" } else { "" };
+    let dirty = if model.file.is_dirty() { " [+]" } else { "" };
+    let file_name = Line::from(format!("[{}]{dirty}{read_only}", model.file.name))
         .alignment(Alignment::Center);
 
-    let cur_mode = match model.mode {
+    // View mode shows the rendered markdown unless the source was toggled
+    // or the preview already sits next to it
+    let show_preview = note_mode == Mode::View && !model.raw && !model.split;
+
+    let cur_mode = match note_mode {
         Mode::Edit => "[EDIT]",
 
+        Mode::View if model.raw && !model.split => "[SOURCE]",
+
+        Mode::Visual => "[VISUAL]",
+
+        Mode::VisualLine => "[VISUAL LINE]",
+
         Mode::View => "[VIEW]",
 
         _ => "",
     };
 
-    let mode = Line::from(cur_mode).alignment(Alignment::Left);
+    // A vim command being typed shows next to the mode
+    let mode = match model.pending.keys() {
+        "" => Line::from(cur_mode),
+        keys => Line::from(format!("{cur_mode} {keys}")),
+    };
+
+    let mode = mode.alignment(Alignment::Left);
+
+    let pos = if show_preview {
+        // Borders take one column on each side
+        let width = chunks[0].width.saturating_sub(2);
+        model.preview.update(model.file.textarea.lines(), width);
 
-    let (y, x) = model.file.textarea.cursor();
-    let pos = Line::from(format!("[{}:{}]", y + 1, x)).alignment(Alignment::Right);
+        format!("[{}/{}]", model.preview.scroll + 1, model.preview.len())
+    } else {
+        let (y, x) = model.file.textarea.cursor();
+        format!("[{}:{}]", y + 1, x)
+    };
+
+    let pos = Line::from(pos).alignment(Alignment::Right);
 
     let line_number_style = Style::new().add_modifier(Modifier::DIM);
     let selection_style = Style::new().add_modifier(Modifier::REVERSED);
@@ -49,7 +224,7 @@ pub fn render_frame(model: &mut Model, f: &mut Frame) {
         Style::new().add_modifier(Modifier::DIM) 
     };
 
-    let cursor_style = if model.mode == Mode::Edit { 
+    let cursor_style = if note_mode == Mode::Edit { 
         Style::new()
             .add_modifier(Modifier::SLOW_BLINK)
             .add_modifier(Modifier::REVERSED) 
@@ -66,6 +241,16 @@ pub fn render_frame(model: &mut Model, f: &mut Frame) {
                 .title_bottom(mode)
                 .title_bottom(pos);
 
+    if show_preview {
+        f.render_stateful_widget(
+            PreviewPane::default().block(block).style(focused_style),
+            chunks[0],
+            &mut model.preview
+        );
+
+        return;
+    }
+
     model.file.textarea.set_block(block);
     
     model.file.textarea.set_line_number_style(line_number_style);
@@ -73,23 +258,47 @@ pub fn render_frame(model: &mut Model, f: &mut Frame) {
     model.file.textarea.set_style(focused_style);
     model.file.textarea.set_cursor_style(cursor_style);
 
-    f.render_widget(
-        model.file.textarea.widget(),
-        chunks[0]
+    // Source on the left, live preview on the right
+    let panes = if model.split {
+        Layout::horizontal([Constraint::Fill(1), Constraint::Fill(1)]).split(chunks[0])
+    } else {
+        chunks.clone()
+    };
+
+    // Only the chunks that changed since the last frame are parsed again
+    model.highlighter.update(model.file.textarea.lines());
+
+    let selection = model.selection();
+
+    f.render_stateful_widget(
+        Editor::new(&model.file.textarea, &model.highlighter)
+            .wrap(model.wrap, model.settings.wrap_column.map(usize::from))
+            .selection(selection, selection_style),
+        panes[0],
+        &mut model.viewport
     );
 
-    //
-    /*
-    if info_space == 1 {
-        let info_block = Block::new()
-                            .borders(Borders::NONE)
-                            .title(format!("{}", model.info.clone().unwrap()))
-                            .title_alignment(Alignment::Center);
+    if model.split {
+        let preview_block = Block::new()
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .border_style(Style::new().add_modifier(Modifier::DIM))
+                    .padding(Padding::top(1))
+                    .title_top(Line::from("[PREVIEW]").alignment(Alignment::Center));
 
-        f.render_widget(
-            info_block,
-            chunks[1]
+        // Keep the block under the cursor at the same height as the cursor
+        let (row, _) = model.file.textarea.cursor();
+        let offset = row.saturating_sub(model.viewport.row);
+
+        let width = panes[1].width.saturating_sub(2);
+        model.preview.update(model.file.textarea.lines(), width);
+        model.preview.height = preview_block.inner(panes[1]).height as usize;
+        model.preview.follow(row, offset);
+
+        f.render_stateful_widget(
+            PreviewPane::default().block(preview_block).style(focused_style),
+            panes[1],
+            &mut model.preview
         );
     }
-    */
 }
\ No newline at end of file
@@ -0,0 +1,761 @@
+use tui_textarea::{CursorMove, TextArea};
+
+use super::{file::File, markdown::reflow};
+
+// Vim's normal mode grammar for the source view:
+// [count] motion, [count] operator [count] (motion | text object | operator again),
+// plus x, p, P and . for repeating the last change, gq reflows like Alt-q
+// In visual mode motions move the cursor and operators apply to the selection right away
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Delete,
+    Change,
+    Yank,
+    Indent,
+    Outdent,
+    // Wraps in `*`, only on a selection
+    Emphasis,
+    // `gq`, rewraps the paragraphs on the lines
+    Reflow,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    WordForward,
+    WordBack,
+    WordEnd,
+    LineStart,
+    LineEnd,
+    FirstLine,
+    LastLine,
+    ParagraphForward,
+    ParagraphBack,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Object {
+    Word,
+    Paragraph,
+    Quote(char),
+}
+
+// What an operator applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Motion(Motion),
+    // `a` instead of `i` takes the surrounding space too
+    Object(Object, bool),
+    // The operator typed twice, `dd`, `>>`
+    Lines,
+    // The visual selection
+    Selection,
+}
+
+// Counts are kept as typed, `gg` and `G` go to a line only when given one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Move(Motion, Option<usize>),
+    Apply(Operator, Target, Option<usize>),
+    // Before the cursor for `P`
+    Put(bool, Option<usize>),
+    Repeat(Option<usize>),
+    // Starts or switches to visual mode, linewise for `V`
+    Visual(bool),
+    // Moves the cursor to the other end of the selection
+    OtherEnd,
+    // Selects a text object
+    Select(Object, bool),
+}
+
+// Keys of a command typed so far
+#[derive(Debug, Default)]
+pub struct Pending {
+    count: Option<usize>,
+    operator: Option<(Operator, Option<usize>)>,
+    // `g`, or `i`/`a` before a text object
+    prefix: Option<char>,
+    keys: String,
+}
+
+enum Step {
+    More,
+    Done(Command),
+    Invalid,
+}
+
+impl Pending {
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    // Shown in the status while a command is half typed
+    pub fn keys(&self) -> &str {
+        &self.keys
+    }
+
+    pub fn clear(&mut self) {
+        *self = Pending::default();
+    }
+
+    // The command once it's complete, a key that doesn't fit drops it
+    pub fn push(&mut self, c: char, visual: bool) -> Option<Command> {
+        self.keys.push(c);
+
+        match self.step(c, visual) {
+            Step::More => None,
+
+            Step::Done(command) => {
+                self.clear();
+                Some(command)
+            },
+
+            Step::Invalid => {
+                self.clear();
+                None
+            },
+        }
+    }
+
+    fn step(&mut self, c: char, visual: bool) -> Step {
+        if let Some(prefix) = self.prefix.take() {
+            return match (prefix, c) {
+                ('g', 'g') => self.target(Target::Motion(Motion::FirstLine)),
+
+                ('g', 'q') if visual => {
+                    Step::Done(Command::Apply(Operator::Reflow, Target::Selection, self.count))
+                },
+
+                ('g', 'q') => match self.operator {
+                    None => {
+                        self.operator = Some((Operator::Reflow, None));
+                        Step::More
+                    },
+
+                    // `gqgq`
+                    Some((Operator::Reflow, _)) => self.target(Target::Lines),
+                    Some(_) => Step::Invalid,
+                },
+
+                ('i' | 'a', c) => match object(c) {
+                    Some(object) => self.target(Target::Object(object, prefix == 'a')),
+                    None => Step::Invalid,
+                },
+
+                _ => Step::Invalid,
+            };
+        }
+
+        // Digits go to the operator's count once there is one, `2d3w` deletes 6 words
+        let count = match &mut self.operator {
+            Some((_, count)) => count,
+            None => &mut self.count,
+        };
+
+        if c.is_ascii_digit() && (c != '0' || count.is_some()) {
+            let digit = c.to_digit(10).unwrap_or(0) as usize;
+            *count = Some(count.unwrap_or(0).saturating_mul(10).saturating_add(digit));
+
+            return Step::More;
+        }
+
+        if visual {
+            let operator = match c {
+                'd' | 'x' => Operator::Delete,
+                'c' => Operator::Change,
+                'y' => Operator::Yank,
+                '>' => Operator::Indent,
+                '<' => Operator::Outdent,
+                '*' => Operator::Emphasis,
+
+                'v' | 'V' => return Step::Done(Command::Visual(c == 'V')),
+                'o' => return Step::Done(Command::OtherEnd),
+                'i' | 'a' => {
+                    self.prefix = Some(c);
+                    return Step::More;
+                },
+
+                'p' | 'P' | '.' => return Step::Invalid,
+                _ => return self.motion(c),
+            };
+
+            return Step::Done(Command::Apply(operator, Target::Selection, self.count));
+        }
+
+        match c {
+
+            'i' | 'a' if self.operator.is_some() => {
+                self.prefix = Some(c);
+                Step::More
+            },
+
+            'd' | 'c' | 'y' | '>' | '<' => {
+                let operator = match c {
+                    'd' => Operator::Delete,
+                    'c' => Operator::Change,
+                    'y' => Operator::Yank,
+                    '>' => Operator::Indent,
+                    _ => Operator::Outdent,
+                };
+
+                match self.operator {
+                    None => {
+                        self.operator = Some((operator, None));
+                        Step::More
+                    },
+
+                    Some((pending, _)) if pending == operator => self.target(Target::Lines),
+
+                    Some(_) => Step::Invalid,
+                }
+            },
+
+            // `gqq`
+            'q' if matches!(self.operator, Some((Operator::Reflow, _))) => self.target(Target::Lines),
+
+            'x' if self.operator.is_none() => {
+                Step::Done(Command::Apply(Operator::Delete, Target::Motion(Motion::Right), self.count))
+            },
+
+            'p' | 'P' if self.operator.is_none() => Step::Done(Command::Put(c == 'P', self.count)),
+
+            '.' if self.operator.is_none() => Step::Done(Command::Repeat(self.count)),
+
+            'v' | 'V' if self.operator.is_none() => Step::Done(Command::Visual(c == 'V')),
+
+            c => self.motion(c),
+        }
+    }
+
+    fn motion(&mut self, c: char) -> Step {
+        if c == 'g' {
+            self.prefix = Some('g');
+            return Step::More;
+        }
+
+        match motion(c) {
+            Some(motion) => self.target(Target::Motion(motion)),
+            None => Step::Invalid,
+        }
+    }
+
+    fn target(&mut self, target: Target) -> Step {
+        let count = match (self.count, self.operator.and_then(|(_, count)| count)) {
+            (Some(a), Some(b)) => Some(a.saturating_mul(b)),
+            (a, b) => a.or(b),
+        };
+
+        match (self.operator, target) {
+            (Some((operator, _)), target) => Step::Done(Command::Apply(operator, target, count)),
+
+            (None, Target::Motion(motion)) => Step::Done(Command::Move(motion, count)),
+
+            // Only visual mode takes `i` and `a` without an operator
+            (None, Target::Object(object, around)) => Step::Done(Command::Select(object, around)),
+
+            (None, _) => Step::Invalid,
+        }
+    }
+}
+
+fn motion(c: char) -> Option<Motion> {
+    Some(match c {
+        'h' => Motion::Left,
+        'l' => Motion::Right,
+        'k' => Motion::Up,
+        'j' => Motion::Down,
+        'w' => Motion::WordForward,
+        'b' => Motion::WordBack,
+        'e' => Motion::WordEnd,
+        '0' => Motion::LineStart,
+        '$' => Motion::LineEnd,
+        'G' => Motion::LastLine,
+        '}' => Motion::ParagraphForward,
+        '{' => Motion::ParagraphBack,
+        _ => return None,
+    })
+}
+
+fn object(c: char) -> Option<Object> {
+    match c {
+        'w' => Some(Object::Word),
+        'p' => Some(Object::Paragraph),
+        '"' | '\'' | '`' => Some(Object::Quote(c)),
+        _ => None,
+    }
+}
+
+// Most times a put or an indent is repeated, a larger count would only hang the editor
+pub const MAX_REPEAT: usize = 1000;
+
+// Text an operator works on
+// `end` is past the last char, linewise spans take whole rows from `start.0` to `end.0`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+    pub linewise: bool,
+}
+
+impl Span {
+    fn lines(start: usize, end: usize) -> Span {
+        Span { start: (start, 0), end: (end, 0), linewise: true }
+    }
+}
+
+// What was deleted or yanked last, put back with `p`
+#[derive(Debug, Clone, Default)]
+pub struct Register {
+    pub text: String,
+    pub linewise: bool,
+}
+
+// Whether a motion's span takes whole lines or includes the char it lands on
+fn linewise(motion: Motion) -> bool {
+    matches!(motion, Motion::Up | Motion::Down | Motion::FirstLine | Motion::LastLine)
+}
+
+fn inclusive(motion: Motion) -> bool {
+    matches!(motion, Motion::WordEnd | Motion::LineEnd)
+}
+
+fn len(lines: &[String], row: usize) -> usize {
+    lines[row].chars().count()
+}
+
+fn blank(line: &str) -> bool {
+    line.trim().is_empty()
+}
+
+fn first_non_blank(line: &str) -> usize {
+    line.chars().take_while(|c| c.is_whitespace()).count()
+}
+
+// Words are runs of letters, digits and `_`, or runs of other non-blank chars
+fn kind(c: char) -> u8 {
+    if c.is_whitespace() { 0 } else if c.is_alphanumeric() || c == '_' { 1 } else { 2 }
+}
+
+pub fn jump(textarea: &mut TextArea, (row, col): (usize, usize)) {
+    let row = row.min(u16::MAX as usize) as u16;
+    let col = col.min(u16::MAX as usize) as u16;
+
+    textarea.move_cursor(CursorMove::Jump(row, col));
+}
+
+// Last char of the word at `col`
+fn run_end(line: &str, col: usize) -> usize {
+    let chars: Vec<char> = line.chars().collect();
+    let word = kind(chars[col]);
+
+    let mut end = col;
+    while end + 1 < chars.len() && kind(chars[end + 1]) == word { end += 1; }
+    end
+}
+
+// Next char that ends a word, across lines
+fn word_end(lines: &[String], (mut row, mut col): (usize, usize)) -> (usize, usize) {
+    let chars = |row: usize| -> Vec<char> { lines[row].chars().collect() };
+    let mut line = chars(row);
+    col += 1;
+
+    // Blanks and line breaks in between
+    loop {
+        while col < line.len() && kind(line[col]) == 0 { col += 1; }
+        if col < line.len() || row + 1 == lines.len() { break; }
+
+        row += 1;
+        col = 0;
+        line = chars(row);
+    }
+
+    if col >= line.len() { return (row, line.len().saturating_sub(1)); }
+
+    (row, run_end(&lines[row], col))
+}
+
+// Applies `step` up to `n` times, stopping once it no longer moves
+fn repeat(n: usize, mut pos: (usize, usize), mut step: impl FnMut((usize, usize)) -> (usize, usize)) -> (usize, usize) {
+    for _ in 0..n {
+        let next = step(pos);
+        if next == pos { break; }
+        pos = next;
+    }
+
+    pos
+}
+
+// Moves the cursor, with CursorMove where it does what vim does
+// Counts larger than the note stop where the cursor can't go further
+pub fn motion_to(textarea: &mut TextArea, motion: Motion, count: Option<usize>) {
+    let (row, col) = textarea.cursor();
+    let lines = textarea.lines();
+    let last = lines.len() - 1;
+    let n = count.unwrap_or(1);
+
+    let to = match motion {
+        Motion::Left => (row, col.saturating_sub(n)),
+        Motion::Right => (row, col.saturating_add(n).min(len(lines, row))),
+
+        Motion::Up | Motion::Down | Motion::WordForward | Motion::WordBack => {
+            let step = match motion {
+                Motion::Up => CursorMove::Up,
+                Motion::Down => CursorMove::Down,
+                Motion::WordForward => CursorMove::WordForward,
+                _ => CursorMove::WordBack,
+            };
+
+            repeat(n, (row, col), |_| {
+                textarea.move_cursor(step);
+                textarea.cursor()
+            });
+            return;
+        },
+
+        Motion::WordEnd => repeat(n, (row, col), |pos| word_end(lines, pos)),
+
+        Motion::LineStart => {
+            textarea.move_cursor(CursorMove::Head);
+            return;
+        },
+
+        Motion::LineEnd => {
+            let row = row.saturating_add(n - 1).min(last);
+            (row, len(lines, row).saturating_sub(1))
+        },
+
+        Motion::FirstLine | Motion::LastLine => {
+            let row = match (motion, count) {
+                (_, Some(n)) => (n - 1).min(last),
+                (Motion::FirstLine, None) => 0,
+                _ => last,
+            };
+
+            (row, first_non_blank(&lines[row]))
+        },
+
+        // To the blank line after the paragraph, the end of the note after the last one
+        Motion::ParagraphForward => {
+            let mut row = row;
+
+            for _ in 0..n.min(lines.len()) {
+                while row <= last && blank(&lines[row]) { row += 1; }
+                while row <= last && !blank(&lines[row]) { row += 1; }
+            }
+
+            if row > last { (last, len(lines, last)) } else { (row, 0) }
+        },
+
+        Motion::ParagraphBack => {
+            let mut row = row as isize;
+
+            for _ in 0..n.min(lines.len()) {
+                while row >= 0 && blank(&lines[row as usize]) { row -= 1; }
+                while row >= 0 && !blank(&lines[row as usize]) { row -= 1; }
+            }
+
+            (row.max(0) as usize, 0)
+        },
+    };
+
+    jump(textarea, to);
+}
+
+// What an operator would work on from the cursor, the cursor stays
+pub fn span(textarea: &mut TextArea, operator: Operator, target: Target, count: Option<usize>) -> Option<Span> {
+    let from = textarea.cursor();
+    let last = textarea.lines().len() - 1;
+
+    match target {
+        Target::Lines => Some(Span::lines(from.0, from.0.saturating_add(count.unwrap_or(1) - 1).min(last))),
+
+        Target::Object(object, around) => object_span(textarea.lines(), from, object, around),
+
+        // Known to the caller, it keeps where the selection started
+        Target::Selection => None,
+
+        Target::Motion(motion) => {
+            let lines = textarea.lines();
+            let on_word = lines[from.0].chars().nth(from.1).is_some_and(|c| kind(c) != 0);
+
+            // `cw` changes to the end of the word, like `ce` without skipping to the next one
+            let change_word = operator == Operator::Change && motion == Motion::WordForward && on_word;
+
+            let to = if change_word {
+                let first = (from.0, run_end(&lines[from.0], from.1));
+                repeat(count.unwrap_or(1) - 1, first, |pos| word_end(lines, pos))
+            } else {
+                motion_to(textarea, motion, count);
+                let to = textarea.cursor();
+                jump(textarea, from);
+                to
+            };
+
+            let lines = textarea.lines();
+            let (start, end) = (from.min(to), from.max(to));
+
+            if linewise(motion) {
+                return Some(Span::lines(start.0, end.0));
+            }
+
+            let end = if inclusive(motion) || change_word {
+                (end.0, (end.1 + 1).min(len(lines, end.0)))
+            } else if motion == Motion::WordForward && end.0 > start.0 {
+                // `dw` on the last word of a line stops at its end
+                (start.0, len(lines, start.0))
+            } else {
+                end
+            };
+
+            (start != end).then_some(Span { start, end, linewise: false })
+        },
+    }
+}
+
+fn object_span(lines: &[String], (row, col): (usize, usize), object: Object, around: bool) -> Option<Span> {
+    let line: Vec<char> = lines[row].chars().collect();
+
+    match object {
+        Object::Word => {
+            let c = *line.get(col)?;
+            let word = kind(c);
+
+            let mut start = col;
+            while start > 0 && kind(line[start - 1]) == word { start -= 1; }
+
+            let mut end = col + 1;
+            while end < line.len() && kind(line[end]) == word { end += 1; }
+
+            // The blanks after the word, or before it when there are none after
+            if around {
+                let after = end;
+                while end < line.len() && kind(line[end]) == 0 { end += 1; }
+
+                if end == after {
+                    while start > 0 && kind(line[start - 1]) == 0 { start -= 1; }
+                }
+            }
+
+            Some(Span { start: (row, start), end: (row, end), linewise: false })
+        },
+
+        // Lines with text around the cursor, or the blank ones if it's on one
+        Object::Paragraph => {
+            let is_blank = blank(&lines[row]);
+            let same = |r: usize| blank(&lines[r]) == is_blank;
+
+            let mut start = row;
+            while start > 0 && same(start - 1) { start -= 1; }
+
+            let mut end = row;
+            while end + 1 < lines.len() && same(end + 1) { end += 1; }
+
+            // The blank lines after it, or before it at the end of the note
+            if around {
+                let after = end;
+                while end + 1 < lines.len() && blank(&lines[end + 1]) != is_blank { end += 1; }
+
+                if end == after {
+                    while start > 0 && blank(&lines[start - 1]) != is_blank { start -= 1; }
+                }
+            }
+
+            Some(Span::lines(start, end))
+        },
+
+        // Quotes pair up from the start of the line,
+        // the first pair after the cursor if it isn't inside one
+        Object::Quote(quote) => {
+            let quotes: Vec<usize> = line.iter().enumerate()
+                .filter(|(i, c)| **c == quote && (*i == 0 || line[i - 1] != '\\'))
+                .map(|(i, _)| i)
+                .collect();
+
+            let (open, close) = quotes.chunks_exact(2)
+                .map(|pair| (pair[0], pair[1]))
+                .find(|(_, close)| *close >= col)?;
+
+            let (mut start, mut end) = if around { (open, close + 1) } else { (open + 1, close) };
+
+            if around {
+                let after = end;
+                while end < line.len() && kind(line[end]) == 0 { end += 1; }
+
+                if end == after {
+                    while start > 0 && kind(line[start - 1]) == 0 { start -= 1; }
+                }
+            }
+
+            Some(Span { start: (row, start), end: (row, end), linewise: false })
+        },
+    }
+}
+
+// Visual selections take the char under the cursor as well
+pub fn selection(lines: &[String], anchor: (usize, usize), cursor: (usize, usize), linewise: bool) -> Span {
+    let (start, end) = (anchor.min(cursor), anchor.max(cursor));
+
+    if linewise {
+        Span::lines(start.0, end.0)
+    } else {
+        Span { start, end: (end.0, (end.1 + 1).min(len(lines, end.0))), linewise: false }
+    }
+}
+
+pub fn text(lines: &[String], span: &Span) -> Register {
+    if span.linewise {
+        return Register { text: lines[span.start.0..=span.end.0].join("\n"), linewise: true };
+    }
+
+    let slice = |row: usize, from: usize, to: Option<usize>| -> String {
+        let chars = lines[row].chars().skip(from);
+        match to {
+            Some(to) => chars.take(to - from).collect(),
+            None => chars.collect(),
+        }
+    };
+
+    let (start, end) = (span.start, span.end);
+
+    let text = if start.0 == end.0 {
+        slice(start.0, start.1, Some(end.1))
+    } else {
+        let mut parts = vec![slice(start.0, start.1, None)];
+        parts.extend(lines[start.0 + 1..end.0].iter().cloned());
+        parts.push(slice(end.0, 0, Some(end.1)));
+        parts.join("\n")
+    };
+
+    Register { text, linewise: false }
+}
+
+// Removes the span, `keep_line` leaves an empty row behind linewise spans, for `cc`
+// The caller groups the edit
+pub fn delete(file: &mut File, span: &Span, keep_line: bool) {
+    let lines = file.textarea.lines();
+    let last = lines.len() - 1;
+    let (first, end_row) = (span.start.0, span.end.0);
+
+    let (from, to) = if !span.linewise {
+        (span.start, span.end)
+    } else if keep_line {
+        ((first, 0), (end_row, len(lines, end_row)))
+    } else if end_row < last {
+        ((first, 0), (end_row + 1, 0))
+    } else if first > 0 {
+        // The last rows take the line break before them
+        ((first - 1, len(lines, first - 1)), (end_row, len(lines, end_row)))
+    } else {
+        ((0, 0), (end_row, len(lines, end_row)))
+    };
+
+    jump(&mut file.textarea, from);
+    file.textarea.start_selection();
+    jump(&mut file.textarea, to);
+
+    let deleted = file.textarea.delete_str(0);
+    file.textarea.cancel_selection();
+    file.step(deleted);
+
+    if span.linewise && !keep_line {
+        let row = first.min(file.textarea.lines().len() - 1);
+        let col = first_non_blank(&file.textarea.lines()[row]);
+        jump(&mut file.textarea, (row, col));
+    }
+}
+
+// `p` puts it after the cursor, lines go below the cursor's
+pub fn put(file: &mut File, register: &Register, before: bool, count: usize) {
+    if register.text.is_empty() && !register.linewise { return; }
+
+    let (row, col) = file.textarea.cursor();
+    let text = vec![register.text.as_str(); count.min(MAX_REPEAT)];
+
+    if register.linewise {
+        let text = text.join("\n");
+
+        let inserted = if before {
+            jump(&mut file.textarea, (row, 0));
+            file.textarea.insert_str(format!("{text}\n"))
+        } else {
+            let end = len(file.textarea.lines(), row);
+            jump(&mut file.textarea, (row, end));
+            file.textarea.insert_str(format!("\n{text}"))
+        };
+        file.step(inserted);
+
+        let row = if before { row } else { row + 1 };
+        let col = first_non_blank(&file.textarea.lines()[row]);
+        jump(&mut file.textarea, (row, col));
+    } else {
+        let end = len(file.textarea.lines(), row);
+        let at = if before { col } else { (col + 1).min(end) };
+        jump(&mut file.textarea, (row, at));
+
+        let inserted = file.textarea.insert_str(text.concat());
+        file.step(inserted);
+
+        // On the last char put
+        file.textarea.move_cursor(CursorMove::Back);
+    }
+}
+
+// Adds or removes one level of indentation on each row with text
+pub fn indent(file: &mut File, span: &Span, outdent: bool) {
+    let (first, last) = (span.start.0, span.end.0);
+    let indent = file.textarea.indent();
+
+    let lines: Vec<String> = file.textarea.lines()[first..=last].iter()
+        .map(|line| match outdent {
+            false if blank(line) => line.clone(),
+            false => format!("{indent}{line}"),
+
+            true if line.starts_with('\t') => line[1..].to_string(),
+            true => {
+                let spaces = line.chars().take(indent.len()).take_while(|c| *c == ' ').count();
+                line[spaces..].to_string()
+            },
+        })
+        .collect();
+
+    file.replace_lines(first..last + 1, lines);
+
+    let col = first_non_blank(&file.textarea.lines()[first]);
+    jump(&mut file.textarea, (first, col));
+}
+
+// Rewraps the paragraphs on the span's lines, the cursor goes to the first
+pub fn reflow(file: &mut File, span: &Span, width: usize) {
+    let rows = span.start.0..span.end.0 + 1;
+    let lines = reflow::reflow(file.textarea.lines(), rows.clone(), width);
+
+    file.replace_lines(rows, lines);
+
+    let col = first_non_blank(&file.textarea.lines()[span.start.0]);
+    jump(&mut file.textarea, (span.start.0, col));
+}
+
+// Wraps the span in `*`, linewise spans from the first char with text
+pub fn emphasize(file: &mut File, span: &Span) {
+    let lines = file.textarea.lines();
+
+    let (start, end) = if span.linewise {
+        let (first, last) = (span.start.0, span.end.0);
+        ((first, first_non_blank(&lines[first])), (last, len(lines, last)))
+    } else {
+        (span.start, span.end)
+    };
+
+    // The end first, so the start stays where it is
+    for pos in [end, start] {
+        jump(&mut file.textarea, pos);
+        let inserted = file.textarea.insert_str("*");
+        file.step(inserted);
+    }
+
+    jump(&mut file.textarea, start);
+}
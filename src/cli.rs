@@ -1,5 +1,5 @@
 use std::path::PathBuf;
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 
 /// Create, Edit and View Markdown notes through your terminal.
 #[derive(Debug, Parser)]
@@ -11,7 +11,8 @@ pub struct Cli {
 
 #[derive(Debug, Subcommand)]
 pub enum Commands {
-    /// Opens file with the specified name, else creates with <filename> in <directory>
+    /// Opens the note that best matches <file>, searching <dir> or the whole notes folder.
+    /// Several matches are listed to pick from, a new file is created only when confirmed
     #[command(short_flag='o')]
     Open { file: String, dir: Option<String> },
 
@@ -19,7 +20,65 @@ pub enum Commands {
     #[command()]
     Config { path: PathBuf },
 
-    /// List folders and/or files [UNIMPLEMENTED]
+    /// List the folders (and optionally files) of the notes folder as a tree
     #[command()]
-    List { dir: Option<String>, list_files: bool }
-}
\ No newline at end of file
+    List {
+        /// Subfolder of the notes folder to list instead of its root
+        dir: Option<String>,
+
+        /// Also list files, not only folders
+        #[arg(short, long)]
+        list_files: bool,
+
+        /// Order of the entries inside each folder
+        #[arg(short, long, value_enum, default_value_t = SortBy::Name)]
+        sort: SortBy,
+
+        /// Reverse the sort order
+        #[arg(short, long)]
+        reverse: bool,
+
+        /// Print the tree as JSON instead of drawing it
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Search the text of every note for a word, a phrase or a pattern
+    #[command()]
+    Search {
+        /// Text to look for
+        query: String,
+
+        /// Subfolder of the notes folder to search instead of its root
+        dir: Option<String>,
+
+        /// Treat the query as a regular expression
+        #[arg(short = 'e', long)]
+        regex: bool,
+
+        /// Match upper and lower case exactly
+        #[arg(short, long)]
+        case_sensitive: bool,
+
+        /// Order of the files in the results
+        #[arg(short, long, value_enum, default_value_t = RankBy::Matches)]
+        rank: RankBy,
+    }
+}
+
+/// Sorting keys for the `list` command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SortBy {
+    Name,
+    Mtime,
+    Size,
+}
+
+/// Ranking of the files for the `search` command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum RankBy {
+    /// Most matches first, the latest edit breaks ties
+    Matches,
+    /// Latest edit first, the number of matches breaks ties
+    Recent,
+}
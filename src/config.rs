@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use confy::ConfyError;
 use serde::{Serialize, Deserialize};
@@ -6,23 +7,74 @@ use serde::{Serialize, Deserialize};
 // It's possible to create multiple config files
 // So, if i want to have some configuration
 // I can always organize it per file
-#[derive(Debug, Serialize, Deserialize)]
+// Missing options fall back to their default, so older config files still load
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Settings {
     pub folder_path: Option<PathBuf>,
     // tab_length: i8, default = 4
+
+    // Wrap long lines on screen instead of scrolling sideways
+    pub soft_wrap: bool,
+    // Column to soft wrap at, the width of the pane if not set
+    pub wrap_column: Option<u16>,
+    // Column paragraphs are reflowed at
+    pub reflow_width: u16,
+    // Align tables and tidy list markers when saving
+    pub format_on_save: bool,
+    // Previous versions kept next to a note when saving, none if 0
+    pub backups: u8,
+    // Save changes when the terminal loses focus
+    // and after `autosave_idle` seconds without input
+    pub autosave: bool,
+    pub autosave_idle: u16,
+    // Seconds between looks at the note on disk for changes made elsewhere,
+    // only when saving if 0
+    pub check_interval: u16,
+    // Seconds between copies of unsaved changes to a swap file in the cache folder,
+    // offered back if the app dies before saving, none if 0
+    pub swap_interval: u16,
+
+    // Notes are saved with the line endings, final newline and BOM they were
+    // opened with, these force one way instead
+    pub line_ending: Option<LineEnding>,
+    pub final_newline: Option<bool>,
+    pub bom: Option<bool>,
+
+    // Keys changed from the defaults, per mode
+    pub keymap: Bindings,
 }
 
 // Default configuration used when
 // creating the config file through confy::load
 impl Default for Settings {
     fn default() -> Self {
-        Settings { folder_path: None }
+        Settings {
+            folder_path: None,
+            soft_wrap: false,
+            wrap_column: None,
+            reflow_width: 80,
+            format_on_save: false,
+            backups: 0,
+            autosave: false,
+            autosave_idle: 10,
+            check_interval: 2,
+            swap_interval: 4,
+            line_ending: None,
+            final_newline: None,
+            bom: None,
+            keymap: Bindings::default(),
+        }
     }
 }
 
 impl Settings {
-    pub fn new(path: PathBuf) -> Settings {
-        Settings { folder_path: Some(path) }
+    pub fn load(appname: &str, config_file_name: Option<&str>) -> Option<Settings> {
+        match confy::load(appname, config_file_name) {
+            Ok(cfg) => Some(cfg),
+
+            Err(e) => { io_err(e); None },
+        }
     }
 
     pub fn store(self, appname: &str, config_file_name: Option<&str>) {
@@ -38,16 +90,45 @@ impl Settings {
     }
 
     pub fn path_exists(appname: &str, config_file_name: Option<&str>) -> Option<PathBuf> {
-        // I don't know if i should use the ? operator, but i don't know a better way
-        let cfg: Settings = confy::load(appname, config_file_name).ok()?;
+        Settings::load(appname, config_file_name)?.folder()
+    }
 
-        if let None = cfg.folder_path {
+    // Notes folder, complains if it hasn't been configured yet
+    pub fn folder(&self) -> Option<PathBuf> {
+        if self.folder_path.is_none() {
             missing_folder_err();
+        }
+
+        self.folder_path.clone()
+    }
+}
+
+// Keys mapped to action names, one table per mode
+// Action "none" unbinds a key, the README lists the keys and actions
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Bindings {
+    pub preview: BTreeMap<String, String>,
+    pub source: BTreeMap<String, String>,
+    pub edit: BTreeMap<String, String>,
+    pub table: BTreeMap<String, String>,
+    pub find: BTreeMap<String, String>,
+}
+
+// Line endings written when saving
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+}
 
-            return None
+impl LineEnding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
         }
-        
-        return Some(cfg.folder_path?)
     }
 }
 
@@ -60,7 +141,7 @@ fn io_err(e: ConfyError) {
 
     let err = clap::Error::raw(
         clap::error::ErrorKind::Io, 
-        e
+        format!("{e}\n")
         ).with_cmd(&cmd);
 
     let _ = err.print();
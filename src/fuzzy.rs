@@ -0,0 +1,149 @@
+// Fuzzy matching of note paths
+// The query has to appear in order, not necessarily in one piece.
+// Matches right after a separator, in one run or in the file name score higher.
+
+const MATCH: i64 = 16;
+const CONSECUTIVE: i64 = 12;
+const WORD_START: i64 = 10;
+const CAMEL: i64 = 8;
+const FILE_NAME: i64 = 4;
+const GAP_START: i64 = 3;
+const GAP: i64 = 1;
+
+// Score and char positions of the best match of `query` in `target`
+// Case is ignored, an empty query matches everything with a score of 0
+pub fn score(query: &str, target: &str) -> Option<(i64, Vec<usize>)> {
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    let chars: Vec<char> = target.chars().collect();
+
+    if query.is_empty() { return Some((0, Vec::new())); }
+    if query.len() > chars.len() { return None; }
+
+    let lower: Vec<char> = chars.iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+
+    let name_start = chars.iter().rposition(|c| *c == '/' || *c == '\\').map_or(0, |i| i + 1);
+
+    let bonus: Vec<i64> = (0..chars.len())
+        .map(|j| {
+            let prev = if j == 0 { None } else { Some(chars[j - 1]) };
+
+            let bonus = match prev {
+                None => WORD_START,
+                Some('/' | '\\' | '-' | '_' | ' ' | '.') => WORD_START,
+                Some(p) if p.is_lowercase() && chars[j].is_uppercase() => CAMEL,
+                _ => 0,
+            };
+
+            bonus + if j >= name_start { FILE_NAME } else { 0 }
+        })
+        .collect();
+
+    // best[i][j]: best score with query[i] matched at target[j]
+    // from[i][j]: where query[i - 1] was matched for that score
+    let mut best = vec![vec![None::<i64>; chars.len()]; query.len()];
+    let mut from = vec![vec![0; chars.len()]; query.len()];
+
+    for (i, q) in query.iter().enumerate() {
+        for j in i..chars.len() {
+            if lower[j] != *q { continue; }
+
+            if i == 0 {
+                best[0][j] = Some(MATCH + bonus[j]);
+                continue;
+            }
+
+            let previous = (i - 1..j)
+                .filter_map(|k| best[i - 1][k].map(|s| (k, s)))
+                .map(|(k, s)| {
+                    let step = if k + 1 == j { CONSECUTIVE } else { -gap(j - k - 1) };
+                    (k, s + step)
+                })
+                .max_by_key(|(k, s)| (*s, *k));
+
+            if let Some((k, s)) = previous {
+                best[i][j] = Some(s + MATCH + bonus[j]);
+                from[i][j] = k;
+            }
+        }
+    }
+
+    let last = query.len() - 1;
+    let (mut j, score) = (0..chars.len())
+        .filter_map(|j| best[last][j].map(|s| (j, s)))
+        .max_by_key(|(j, s)| (*s, *j))?;
+
+    let mut positions = vec![0; query.len()];
+    for i in (0..query.len()).rev() {
+        positions[i] = j;
+        j = from[i][j];
+    }
+
+    // Shorter paths win ties
+    Some((score - chars.len() as i64 / 8, positions))
+}
+
+fn gap(len: usize) -> i64 {
+    if len == 0 { 0 } else { GAP_START + GAP * (len as i64 - 1) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rank<'a>(query: &str, targets: &[&'a str]) -> Vec<&'a str> {
+        let mut scored: Vec<(i64, &str)> = targets.iter()
+            .filter_map(|target| score(query, target).map(|(score, _)| (score, *target)))
+            .collect();
+
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        scored.into_iter().map(|(_, target)| target).collect()
+    }
+
+    #[test]
+    fn word_starts_beat_runs_inside_words_beat_scattered_chars() {
+        assert_eq!(
+            rank("plan", &["xpxlxaxn.md", "explanation.md", "weekly-plan.md"]),
+            ["weekly-plan.md", "explanation.md", "xpxlxaxn.md"],
+        );
+
+        // The start of the path is a word start like any other
+        assert_eq!(score("plan", "planets.md").unwrap().0, score("plan", "x-plan.md").unwrap().0);
+    }
+
+    #[test]
+    fn matches_in_the_file_name_beat_the_folders() {
+        assert_eq!(rank("todo", &["todo/list.md", "misc/todo.md"]), ["misc/todo.md", "todo/list.md"]);
+    }
+
+    #[test]
+    fn camel_case_humps_count_as_word_starts() {
+        assert_eq!(rank("mn", &["xmxnx.md", "myNotes.md"]), ["myNotes.md", "xmxnx.md"]);
+    }
+
+    #[test]
+    fn shorter_paths_win_ties() {
+        assert_eq!(rank("a", &["a-very-long-name.md", "a.md"]), ["a.md", "a-very-long-name.md"]);
+    }
+
+    #[test]
+    fn positions_count_chars_not_bytes() {
+        assert_eq!(score("été", "notes/été.md").unwrap().1, [6, 7, 8]);
+        assert_eq!(score("日記", "2026/日記.md").unwrap().1, [5, 6]);
+    }
+
+    #[test]
+    fn case_is_ignored_beyond_ascii() {
+        assert!(score("ÉTÉ", "été.md").is_some());
+        assert!(score("straße", "STRASSE.md").is_none());
+        assert_eq!(score("Ωμέγα", "ωμέγα.md").unwrap().1, [0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn every_query_char_has_to_appear_in_order() {
+        assert!(score("abc", "cba.md").is_none());
+        assert!(score("abcdefghijklmnop", "abc.md").is_none());
+        assert_eq!(score("", "any.md"), Some((0, Vec::new())));
+    }
+}
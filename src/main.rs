@@ -6,6 +6,16 @@ use config::Settings;
 
 pub mod app;
 
+pub mod fuzzy;
+
+pub mod open;
+
+pub mod notebook;
+use notebook::ListOptions;
+
+pub mod search;
+use search::SearchOptions;
+
 use clap::Parser;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -16,31 +26,58 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     match args.command {
         Commands::Config { path } => {
-            // Stores/Updates path in config file
-            Settings::new(path).store(appname, Some(config_file_name))
+            // Stores/Updates path in config file, keeping the other options
+            // A config file that doesn't load is left as is, the error says why
+            let Some(mut cfg) = Settings::load(appname, Some(config_file_name)) else {
+                return Ok(());
+            };
+
+            cfg.folder_path = Some(path);
+
+            cfg.store(appname, Some(config_file_name))
         },
 
-        // TODO: List command
-        Commands::List { dir: _, list_files: _ } => { unimplemented!() },
+        Commands::List { dir, list_files, sort, reverse, json } => {
+            // Check if folder has been configured and returns path
+            if let Some(folderpath) = Settings::path_exists(appname, Some(config_file_name)) {
+                let opts = ListOptions { list_files, sort, reverse };
 
-        Commands::Open { file, dir } => {
+                notebook::list(folderpath, dir, opts, json);
+            }
+        },
+
+        Commands::Search { query, dir, regex, case_sensitive, rank } => {
             // Check if folder has been configured and returns path
-            if let Some(mut filepath) = Settings::path_exists(appname, Some(config_file_name)) {
+            if let Some(folderpath) = Settings::path_exists(appname, Some(config_file_name)) {
+                let opts = SearchOptions { regex, case_sensitive, rank };
 
-                // Append dir to path if the arg exists
-                if let Some(folder) = dir {
-                    filepath.push(folder);
-                }
+                search::run(folderpath, &query, dir, opts);
+            }
+        },
 
-                // Append file to path
-                filepath.push(file);
+        Commands::Open { file, dir } => {
+            let Some(settings) = Settings::load(appname, Some(config_file_name)) else {
+                return Ok(());
+            };
+
+            // Check if folder has been configured and returns path
+            let Some(folderpath) = settings.folder() else { return Ok(()) };
+
+            // Unknown actions and clashing keys are reported instead of ignored
+            if let Err(errors) = app::keymap::Keymap::new(&settings.keymap) {
+                app::keymap::keymap_err(errors);
+                return Ok(());
+            }
 
+            // Look for the note through the whole notebook
+            if let Some(filepath) = open::resolve(&folderpath, &file, dir.as_deref()) {
                 // open file
-                let f = app::file::File::open(filepath);
-                let app = app::Model::new(f);
+                let f = app::file::File::load(filepath);
+                let app = app::Model::new(f, settings);
                 
                 // init
-                let _ = app.init();
+                // The terminal is restored by now, errors print on the normal screen
+                if let Err(e) = app.init() { app::app_err(e) }
             }
         },
     }
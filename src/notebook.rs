@@ -0,0 +1,397 @@
+use std::{
+    fs,
+    io,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+use serde::Serialize;
+
+use crate::cli::SortBy;
+
+// Options for walking the notes folder
+#[derive(Debug, Clone, Copy)]
+pub struct ListOptions {
+    pub list_files: bool,
+    pub sort: SortBy,
+    pub reverse: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    Dir,
+    File,
+}
+
+// A node of the notebook tree
+// Paths are relative to the listed folder so the output
+// doesn't depend on where the notes folder lives
+#[derive(Debug, Serialize)]
+pub struct Entry {
+    pub name: String,
+    pub path: PathBuf,
+    pub kind: EntryKind,
+    // Folders report the size of everything below them
+    pub size: u64,
+    // Seconds since the unix epoch
+    pub modified: u64,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<Entry>,
+}
+
+// Hidden entries (.git, .obsidian, ...) are never part of the notebook
+pub fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with('.'))
+}
+
+pub fn tree(root: &Path, opts: ListOptions) -> io::Result<Entry> {
+    let meta = fs::metadata(root)
+        .map_err(|e| io::Error::new(e.kind(), format!("'{}': {e}", root.display())))?;
+
+    if !meta.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("'{}' is not a folder", root.display())
+        ));
+    }
+
+    let children = read_dir(root, Path::new(""), opts)?;
+
+    let name = root.file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(".")
+        .to_string();
+
+    // Like every other folder, the root counts its own files when they aren't listed
+    let size = children.iter().map(|c| c.size).sum::<u64>()
+        + if opts.list_files { 0 } else { dir_files_size(root) };
+
+    Ok(Entry {
+        name,
+        path: PathBuf::new(),
+        kind: EntryKind::Dir,
+        size,
+        modified: modified(&meta),
+        children,
+    })
+}
+
+fn read_dir(dir: &Path, rel: &Path, opts: ListOptions) -> io::Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+
+    for item in fs::read_dir(dir)? {
+        // One unreadable entry shouldn't hide the rest of the notebook
+        let (item, meta) = match item.and_then(|item| item.metadata().map(|meta| (item, meta))) {
+            Ok(found) => found,
+            Err(e) => { warn(dir, e); continue; },
+        };
+        let path = item.path();
+
+        if is_hidden(&path) { continue; }
+
+        let name = item.file_name().to_string_lossy().into_owned();
+        let rel_path = rel.join(&name);
+
+        if meta.is_dir() {
+            // Folders always need to be walked, their size depends on their files
+            let children = match read_dir(&path, &rel_path, opts) {
+                Ok(children) => children,
+                Err(e) => { warn(&path, e); continue; },
+            };
+            let size = children.iter().map(|c| c.size).sum::<u64>()
+                + if opts.list_files { 0 } else { dir_files_size(&path) };
+
+            entries.push(Entry {
+                name,
+                path: rel_path,
+                kind: EntryKind::Dir,
+                size,
+                modified: modified(&meta),
+                children,
+            });
+        } else if opts.list_files {
+            entries.push(Entry {
+                name,
+                path: rel_path,
+                kind: EntryKind::File,
+                size: meta.len(),
+                modified: modified(&meta),
+                children: Vec::new(),
+            });
+        }
+    }
+
+    sort(&mut entries, opts);
+
+    Ok(entries)
+}
+
+// Size of the files directly inside a folder
+// Only needed when files are left out of the tree,
+// entries that can't be read were already reported by `read_dir`
+fn dir_files_size(dir: &Path) -> u64 {
+    let mut size = 0;
+
+    for item in fs::read_dir(dir).into_iter().flatten().flatten() {
+        if is_hidden(&item.path()) { continue; }
+
+        if let Ok(meta) = item.metadata() {
+            if meta.is_file() { size += meta.len(); }
+        }
+    }
+
+    size
+}
+
+// Parts of the notebook that can't be read are skipped with a warning
+pub fn warn(path: &Path, e: io::Error) {
+    eprintln!("warning: skipping '{}': {e}", path.display());
+}
+
+// Seconds since the unix epoch, 0 if the platform can't tell
+pub fn modified(meta: &fs::Metadata) -> u64 {
+    meta.modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// Extensions of the files that count as notes
+pub fn is_note(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ["md", "markdown", "mdown", "mkd"].contains(&ext.to_lowercase().as_str()))
+}
+
+// Every note below `dir`, in no particular order
+// Symlinked folders aren't followed, like in `list`, so a link back up can't loop.
+// Entries that can't be read go to `skip` and the walk goes on
+pub fn notes(dir: &Path, skip: &mut impl FnMut(&Path, io::Error)) -> io::Result<Vec<PathBuf>> {
+    let mut found = Vec::new();
+
+    for item in fs::read_dir(dir)? {
+        let (path, kind) = match item.and_then(|item| item.file_type().map(|kind| (item.path(), kind))) {
+            Ok(found) => found,
+            Err(e) => { skip(dir, e); continue; },
+        };
+
+        if is_hidden(&path) { continue; }
+
+        if kind.is_dir() {
+            match notes(&path, skip) {
+                Ok(notes) => found.extend(notes),
+                Err(e) => skip(&path, e),
+            }
+        } else if is_note(&path) {
+            found.push(path);
+        }
+    }
+
+    Ok(found)
+}
+
+// Folders first, then files, each ordered by the chosen key
+fn sort(entries: &mut [Entry], opts: ListOptions) {
+    entries.sort_by(|a, b| {
+        let order = match opts.sort {
+            SortBy::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            SortBy::Mtime => a.modified.cmp(&b.modified),
+            SortBy::Size => a.size.cmp(&b.size),
+        };
+
+        let order = if opts.reverse { order.reverse() } else { order };
+
+        // Ties are broken by name so the output is stable
+        (b.kind == EntryKind::Dir).cmp(&(a.kind == EntryKind::Dir))
+            .then(order)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+}
+
+// Draws the tree the same way `tree` does
+pub fn draw(root: &Entry) -> String {
+    let mut out = format!("{}/\n", root.name);
+    draw_children(&root.children, "", &mut out);
+    out
+}
+
+fn draw_children(children: &[Entry], prefix: &str, out: &mut String) {
+    for (i, child) in children.iter().enumerate() {
+        let last = i + 1 == children.len();
+        let (branch, indent) = if last { ("└── ", "    ") } else { ("├── ", "│   ") };
+        let suffix = if child.kind == EntryKind::Dir { "/" } else { "" };
+
+        out.push_str(&format!("{prefix}{branch}{}{suffix}\n", child.name));
+
+        draw_children(&child.children, &format!("{prefix}{indent}"), out);
+    }
+}
+
+// The subfolder `dir` of the notes folder
+// `..` or an absolute path would reach something that isn't part of the notebook,
+// a folder that doesn't exist is left for the caller to report
+pub fn scope(root: &Path, dir: &str) -> io::Result<PathBuf> {
+    let path = root.join(dir);
+
+    if let (Ok(root), Ok(scope)) = (fs::canonicalize(root), fs::canonicalize(&path)) {
+        if !scope.starts_with(root) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("'{dir}' is outside the notes folder")
+            ));
+        }
+    }
+
+    Ok(path)
+}
+
+pub fn list(root: PathBuf, dir: Option<String>, opts: ListOptions, json: bool) {
+    // Scope to a subfolder if the arg exists
+    let path = match dir {
+        Some(folder) => match scope(&root, &folder) {
+            Ok(path) => path,
+            Err(e) => { return list_err(e) },
+        },
+        None => root,
+    };
+
+    let entry = match tree(&path, opts) {
+        Ok(entry) => entry,
+        Err(e) => { return list_err(e) },
+    };
+
+    if json {
+        match serde_json::to_string_pretty(&entry) {
+            Ok(s) => println!("{s}"),
+            Err(e) => list_err(e.into()),
+        }
+    } else {
+        print!("{}", draw(&entry));
+    }
+}
+
+fn list_err(e: io::Error) {
+    let cmd = clap::Command::new("list");
+
+    let err = clap::Error::raw(
+        clap::error::ErrorKind::Io,
+        format!("{e}\n")
+        ).with_cmd(&cmd);
+
+    let _ = err.print();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    // root/
+    //   a.md      5 bytes, newest
+    //   big/      b.md 30 bytes, oldest
+    //   Small/    c.md 1 byte, .hidden.md
+    fn notebook() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |path: &str, len: usize, age: u64| {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, "x".repeat(len)).unwrap();
+
+            let time = SystemTime::now() - Duration::from_secs(age);
+            fs::File::options().write(true).open(&path).unwrap().set_modified(time).unwrap();
+            fs::File::open(path.parent().unwrap()).unwrap().set_modified(time).unwrap();
+        };
+
+        write("big/b.md", 30, 300);
+        write("Small/c.md", 1, 200);
+        write("Small/.hidden.md", 100, 200);
+        write("a.md", 5, 100);
+
+        dir
+    }
+
+    fn names(entry: &Entry) -> Vec<&str> {
+        entry.children.iter().map(|c| c.name.as_str()).collect()
+    }
+
+    fn opts(list_files: bool, sort: SortBy, reverse: bool) -> ListOptions {
+        ListOptions { list_files, sort, reverse }
+    }
+
+    #[test]
+    fn folders_come_first_in_the_chosen_order() {
+        let dir = notebook();
+
+        let entry = tree(dir.path(), opts(true, SortBy::Name, false)).unwrap();
+        assert_eq!(names(&entry), ["big", "Small", "a.md"]);
+
+        let entry = tree(dir.path(), opts(true, SortBy::Name, true)).unwrap();
+        assert_eq!(names(&entry), ["Small", "big", "a.md"]);
+
+        let entry = tree(dir.path(), opts(true, SortBy::Size, false)).unwrap();
+        assert_eq!(names(&entry), ["Small", "big", "a.md"]);
+
+        let entry = tree(dir.path(), opts(true, SortBy::Mtime, false)).unwrap();
+        assert_eq!(names(&entry), ["big", "Small", "a.md"]);
+    }
+
+    #[test]
+    fn sizes_add_up_with_or_without_files() {
+        let dir = notebook();
+
+        for list_files in [true, false] {
+            let entry = tree(dir.path(), opts(list_files, SortBy::Name, false)).unwrap();
+            let sizes: Vec<u64> = entry.children.iter().map(|c| c.size).collect();
+
+            assert_eq!(entry.size, 36);
+            assert_eq!(sizes[..2], [30, 1]);
+        }
+    }
+
+    #[test]
+    fn json_has_relative_paths_and_no_empty_children() {
+        let dir = notebook();
+        let entry = tree(dir.path(), opts(true, SortBy::Name, false)).unwrap();
+        let json = serde_json::to_value(&entry).unwrap();
+
+        let small = &json["children"][1];
+        assert_eq!(small["name"], "Small");
+        assert_eq!(small["kind"], "dir");
+        assert_eq!(small["size"], 1);
+        assert_eq!(small["children"][0]["path"], "Small/c.md");
+        assert_eq!(small["children"][0]["kind"], "file");
+        assert!(small["children"][0].get("children").is_none());
+    }
+
+    #[test]
+    fn only_folders_inside_the_notebook_can_be_listed() {
+        let dir = notebook();
+
+        assert_eq!(scope(dir.path(), "big").unwrap(), dir.path().join("big"));
+        assert!(scope(dir.path(), "big/../Small").is_ok());
+        assert!(scope(dir.path(), "missing").is_ok());
+
+        let outside = scope(dir.path(), "..").unwrap_err();
+        assert_eq!(outside.kind(), io::ErrorKind::InvalidInput);
+        assert!(scope(dir.path(), "/").is_err());
+    }
+
+    #[test]
+    fn drawing_nests_the_folders() {
+        let dir = notebook();
+        let mut entry = tree(dir.path(), opts(true, SortBy::Name, false)).unwrap();
+        entry.name = "notes".to_string();
+
+        assert_eq!(draw(&entry), "\
+notes/
+├── big/
+│   └── b.md
+├── Small/
+│   └── c.md
+└── a.md
+");
+    }
+}
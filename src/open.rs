@@ -0,0 +1,246 @@
+use std::{
+    io::{self, BufRead, IsTerminal, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{fuzzy, notebook};
+
+// Most fuzzy matches offered in the pick list
+const MAX_CANDIDATES: usize = 9;
+
+// Notes `find` turned up for a name
+#[derive(Debug, PartialEq, Eq)]
+enum Found {
+    Note(PathBuf),
+    // Several with the same name, or the closest fuzzy matches
+    Choices(Vec<PathBuf>),
+    Nothing,
+}
+
+// Finds the note `open` should edit, looking through every folder below
+// the notes folder (or `dir` in it). Exact names win over case-insensitive
+// ones, which win over fuzzy ones. A new file is only created when confirmed.
+// None when nothing should be opened.
+pub fn resolve(root: &Path, file: &str, dir: Option<&str>) -> Option<PathBuf> {
+    let scope = dir.map_or_else(|| root.to_path_buf(), |dir| root.join(dir));
+    let new = new_note(&scope.join(file));
+
+    match find(&scope, file) {
+        Ok(Found::Note(note)) => Some(note),
+        Ok(Found::Choices(notes)) => pick(root, file, &notes, &new),
+        Ok(Found::Nothing) => confirm(root, file, &new),
+
+        Err(e) => {
+            open_err(format!("'{}': {e}", scope.display()));
+            None
+        },
+    }
+}
+
+fn find(scope: &Path, file: &str) -> io::Result<Found> {
+    let direct = scope.join(file);
+
+    if direct.is_file() { return Ok(Found::Note(direct)); }
+
+    // Nothing to look through in a folder that isn't there yet, saving creates it
+    if !scope.exists() { return Ok(Found::Nothing); }
+
+    let notes = notebook::notes(scope, &mut notebook::warn)?;
+
+    for case_sensitive in [true, false] {
+        let mut found: Vec<PathBuf> = notes.iter()
+            .filter(|note| names(note, scope).iter().any(|name| same(name, file, case_sensitive)))
+            .cloned()
+            .collect();
+
+        found.sort();
+
+        match found.len() {
+            0 => continue,
+            1 => return Ok(Found::Note(found.remove(0))),
+            _ => return Ok(Found::Choices(found)),
+        }
+    }
+
+    let mut fuzzy: Vec<(i64, &PathBuf)> = notes.iter()
+        .filter_map(|note| {
+            let rel = note.strip_prefix(scope).unwrap_or(note);
+            fuzzy::score(file, &rel.to_string_lossy()).map(|(score, _)| (score, note))
+        })
+        .collect();
+
+    fuzzy.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+
+    let fuzzy: Vec<PathBuf> = fuzzy.into_iter()
+        .take(MAX_CANDIDATES)
+        .map(|(_, note)| note.clone())
+        .collect();
+
+    Ok(if fuzzy.is_empty() { Found::Nothing } else { Found::Choices(fuzzy) })
+}
+
+// What a note can be called: its path with or without extension, or its name
+fn names(note: &Path, scope: &Path) -> Vec<String> {
+    let rel = note.strip_prefix(scope).unwrap_or(note);
+
+    let names = [
+        Some(rel.to_path_buf()),
+        Some(rel.with_extension("")),
+        rel.file_name().map(PathBuf::from),
+        rel.file_stem().map(PathBuf::from),
+    ];
+
+    names.into_iter()
+        .flatten()
+        .map(|name| name.to_string_lossy().into_owned())
+        .collect()
+}
+
+fn same(name: &str, file: &str, case_sensitive: bool) -> bool {
+    if case_sensitive { name == file } else { name.to_lowercase() == file.to_lowercase() }
+}
+
+// Notes without an extension get `.md` so they show up in the notebook
+fn new_note(path: &Path) -> PathBuf {
+    if path.extension().is_some() { path.to_path_buf() } else { path.with_extension("md") }
+}
+
+fn display(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root).unwrap_or(path).display().to_string()
+}
+
+fn pick(root: &Path, file: &str, found: &[PathBuf], new: &Path) -> Option<PathBuf> {
+    println!("Notes matching '{file}':");
+
+    for (i, note) in found.iter().enumerate() {
+        println!("  {}) {}", i + 1, display(root, note));
+    }
+
+    println!("  n) Create {}", display(root, new));
+
+    // The choice can't be asked for when input is piped
+    if !io::stdin().is_terminal() {
+        open_err(format!("No note is exactly '{file}', run again with one of the paths above"));
+        return None;
+    }
+
+    let answer = ask(&format!("Open [1-{}/n, Enter = 1, anything else cancels]: ", found.len()))?;
+
+    choose(&answer, found, new)
+}
+
+// The note an answer to the pick list stands for
+fn choose(answer: &str, found: &[PathBuf], new: &Path) -> Option<PathBuf> {
+    match answer {
+        "" => found.first().cloned(),
+        "n" | "N" => Some(new.to_path_buf()),
+        _ => answer.parse::<usize>().ok()
+            .and_then(|i| i.checked_sub(1))
+            .and_then(|i| found.get(i))
+            .cloned(),
+    }
+}
+
+fn confirm(root: &Path, file: &str, new: &Path) -> Option<PathBuf> {
+    if !io::stdin().is_terminal() {
+        open_err(format!("No note matches '{file}'"));
+        return None;
+    }
+
+    let answer = ask(&format!("No note matches '{file}'. Create {}? [y/N]: ", display(root, new)))?;
+
+    matches!(answer.as_str(), "y" | "Y" | "yes").then(|| new.to_path_buf())
+}
+
+// Reads one trimmed line after showing `prompt`
+fn ask(prompt: &str) -> Option<String> {
+    print!("{prompt}");
+    io::stdout().flush().ok()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer).ok()?;
+
+    Some(answer.trim().to_string())
+}
+
+fn open_err(msg: String) {
+    let cmd = clap::Command::new("open");
+
+    let err = clap::Error::raw(
+        clap::error::ErrorKind::InvalidValue,
+        format!("{msg}\n")
+        ).with_cmd(&cmd);
+
+    let _ = err.print();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn notebook(notes: &[&str]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+
+        for note in notes {
+            let path = dir.path().join(note);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+
+        dir
+    }
+
+    fn found(dir: &tempfile::TempDir, notes: &[&str]) -> Found {
+        Found::Choices(notes.iter().map(|note| dir.path().join(note)).collect())
+    }
+
+    #[test]
+    fn exact_names_win_over_other_case_and_fuzzy_ones() {
+        let dir = notebook(&["Todo.md", "ideas/todo.md", "ideas/todos-old.md"]);
+        let root = dir.path();
+
+        assert_eq!(find(root, "todo").unwrap(), Found::Note(root.join("ideas/todo.md")));
+        assert_eq!(find(root, "Todo.md").unwrap(), Found::Note(root.join("Todo.md")));
+        assert_eq!(find(root, "ideas/todos-old").unwrap(), Found::Note(root.join("ideas/todos-old.md")));
+    }
+
+    #[test]
+    fn the_same_name_twice_is_a_choice_sorted_by_path() {
+        let dir = notebook(&["b/Plan.md", "a/plan.md", "c/plan.md"]);
+
+        assert_eq!(find(dir.path(), "plan").unwrap(), found(&dir, &["a/plan.md", "c/plan.md"]));
+        assert_eq!(find(dir.path(), "PLAN").unwrap(), found(&dir, &["a/plan.md", "b/Plan.md", "c/plan.md"]));
+    }
+
+    #[test]
+    fn fuzzy_matches_are_ranked_then_sorted_by_path() {
+        let dir = notebook(&["recipes.md", "b/rcp.md", "a/rcp.md", "other.md"]);
+
+        let Found::Choices(notes) = find(dir.path(), "rcp.m").unwrap() else { panic!("no choices") };
+        assert_eq!(notes[..2], [dir.path().join("a/rcp.md"), dir.path().join("b/rcp.md")]);
+        assert!(!notes.contains(&dir.path().join("other.md")));
+    }
+
+    #[test]
+    fn a_missing_folder_offers_to_create_the_note() {
+        let dir = notebook(&["new.md"]);
+
+        assert_eq!(find(&dir.path().join("ideas/2026"), "new.md").unwrap(), Found::Nothing);
+        assert_eq!(find(dir.path(), "zzz").unwrap(), Found::Nothing);
+        assert_eq!(new_note(&dir.path().join("ideas/2026/new")), dir.path().join("ideas/2026/new.md"));
+    }
+
+    #[test]
+    fn answers_pick_a_note_or_the_new_one() {
+        let found = [PathBuf::from("a.md"), PathBuf::from("b.md")];
+        let new = Path::new("new.md");
+
+        assert_eq!(choose("", &found, new), Some(PathBuf::from("a.md")));
+        assert_eq!(choose("2", &found, new), Some(PathBuf::from("b.md")));
+        assert_eq!(choose("n", &found, new), Some(PathBuf::from("new.md")));
+        assert_eq!(choose("0", &found, new), None);
+        assert_eq!(choose("3", &found, new), None);
+        assert_eq!(choose("x", &found, new), None);
+    }
+}
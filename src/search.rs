@@ -0,0 +1,288 @@
+use std::{
+    fs,
+    io::{self, IsTerminal},
+    ops::Range,
+    path::{Path, PathBuf},
+};
+use clap::builder::styling::{AnsiColor, Style};
+use regex::{Regex, RegexBuilder};
+
+use crate::cli::RankBy;
+use crate::notebook;
+
+// Long lines are cut around the first match
+const SNIPPET_WIDTH: usize = 100;
+const SNIPPET_CONTEXT: usize = 30;
+
+#[derive(Debug, Clone, Copy)]
+pub struct SearchOptions {
+    pub regex: bool,
+    pub case_sensitive: bool,
+    pub rank: RankBy,
+}
+
+// A line with at least one match
+#[derive(Debug)]
+pub struct Hit {
+    // Both start at 1, the column counts chars
+    pub line: usize,
+    pub column: usize,
+    pub text: String,
+    // Byte ranges of the matches in `text`
+    pub ranges: Vec<Range<usize>>,
+}
+
+// The matches of one note
+#[derive(Debug)]
+pub struct Results {
+    pub path: PathBuf,
+    pub modified: u64,
+    pub hits: Vec<Hit>,
+}
+
+impl Results {
+    pub fn count(&self) -> usize {
+        self.hits.iter().map(|hit| hit.ranges.len()).sum()
+    }
+}
+
+// Plain queries are matched literally
+pub fn pattern(query: &str, opts: SearchOptions) -> Result<Regex, regex::Error> {
+    let query = if opts.regex { query.to_string() } else { regex::escape(query) };
+
+    RegexBuilder::new(&query)
+        .case_insensitive(!opts.case_sensitive)
+        .build()
+}
+
+pub fn search_file(path: &Path, pattern: &Regex) -> io::Result<Vec<Hit>> {
+    // Notes aren't always valid UTF-8, a bad byte shouldn't hide the rest
+    let content = fs::read(path)?;
+    let content = String::from_utf8_lossy(&content);
+
+    let hits = content.lines()
+        .enumerate()
+        .filter_map(|(i, text)| {
+            // Patterns like `a*` match everywhere, empty matches mean nothing
+            let ranges: Vec<Range<usize>> = pattern.find_iter(text)
+                .filter(|m| !m.is_empty())
+                .map(|m| m.range())
+                .collect();
+
+            let first = ranges.first()?;
+
+            Some(Hit {
+                line: i + 1,
+                column: text[..first.start].chars().count() + 1,
+                text: text.to_string(),
+                ranges,
+            })
+        })
+        .collect();
+
+    Ok(hits)
+}
+
+// Every note below `dir` with a match, best ranked first
+pub fn search(dir: &Path, pattern: &Regex, rank: RankBy) -> io::Result<Vec<Results>> {
+    let mut results = Vec::new();
+
+    let notes = notebook::notes(dir, &mut notebook::warn)
+        .map_err(|e| io::Error::new(e.kind(), format!("'{}': {e}", dir.display())))?;
+
+    for path in notes {
+        let hits = match search_file(&path, pattern) {
+            Ok(hits) => hits,
+            Err(e) => { notebook::warn(&path, e); continue; },
+        };
+
+        if hits.is_empty() { continue; }
+
+        let modified = fs::metadata(&path).map(|meta| notebook::modified(&meta)).unwrap_or(0);
+
+        results.push(Results {
+            path,
+            modified,
+            hits,
+        });
+    }
+
+    results.sort_by(|a, b| {
+        let matches = b.count().cmp(&a.count());
+        let recent = b.modified.cmp(&a.modified);
+
+        // Ties are broken by path so the output is stable
+        match rank {
+            RankBy::Matches => matches.then(recent),
+            RankBy::Recent => recent.then(matches),
+        }.then_with(|| a.path.cmp(&b.path))
+    });
+
+    Ok(results)
+}
+
+// The line without its indentation, cut to fit around the first match
+fn snippet(hit: &Hit, color: bool) -> String {
+    let highlight = if color { AnsiColor::Red.on_default().bold() } else { Style::new() };
+
+    let text = hit.text.trim_start();
+    let indent = hit.text.len() - text.len();
+
+    // A match can start in the indentation, it's shown from the first char kept
+    let column = (hit.column - 1).saturating_sub(hit.text[..indent].chars().count());
+    let ranges: Vec<Range<usize>> = hit.ranges.iter()
+        .map(|r| r.start.max(indent) - indent..r.end.max(indent) - indent)
+        .collect();
+
+    let len = text.chars().count();
+    let start = if len > SNIPPET_WIDTH { column.saturating_sub(SNIPPET_CONTEXT) } else { 0 };
+    let end = (start + SNIPPET_WIDTH).min(len);
+
+    let mut out = String::new();
+    let mut in_match = false;
+
+    if start > 0 { out.push('…'); }
+
+    for (byte, c) in text.char_indices().skip(start).take(end - start) {
+        let inside = ranges.iter().any(|r| r.contains(&byte));
+
+        if inside != in_match {
+            if inside {
+                out.push_str(&highlight.render().to_string());
+            } else {
+                out.push_str(&highlight.render_reset().to_string());
+            }
+
+            in_match = inside;
+        }
+
+        out.push(c);
+    }
+
+    if in_match { out.push_str(&highlight.render_reset().to_string()); }
+    if end < len { out.push('…'); }
+
+    out
+}
+
+pub fn run(root: PathBuf, query: &str, dir: Option<String>, opts: SearchOptions) {
+    let mut path = root.clone();
+
+    // Scope to a subfolder if the arg exists
+    if let Some(folder) = dir {
+        path.push(folder);
+    }
+
+    let pattern = match pattern(query, opts) {
+        Ok(pattern) => pattern,
+        Err(e) => { return search_err(clap::error::ErrorKind::ValueValidation, e.to_string()) },
+    };
+
+    let results = match search(&path, &pattern, opts.rank) {
+        Ok(results) => results,
+        Err(e) => { return search_err(clap::error::ErrorKind::Io, e.to_string()) },
+    };
+
+    if results.is_empty() {
+        println!("No matches for '{query}'");
+        return;
+    }
+
+    // Colors only make sense on a terminal, not when piped
+    let color = io::stdout().is_terminal();
+    let (file_style, line_style) = if color {
+        (AnsiColor::Magenta.on_default().bold(), AnsiColor::Green.on_default())
+    } else {
+        (Style::new(), Style::new())
+    };
+
+    for results in &results {
+        // Paths as they are passed to `open`
+        let path = results.path.strip_prefix(&root).unwrap_or(&results.path);
+
+        println!(
+            "{}{}{} ({})",
+            file_style.render(), path.display(), file_style.render_reset(),
+            plural(results.count(), "match", "matches")
+        );
+
+        let positions: Vec<String> = results.hits.iter()
+            .map(|hit| format!("{}:{}", hit.line, hit.column))
+            .collect();
+        let width = positions.iter().map(String::len).max().unwrap_or(0);
+
+        for (hit, position) in results.hits.iter().zip(positions) {
+            println!(
+                "  {}{position:<width$}{}  {}",
+                line_style.render(), line_style.render_reset(),
+                snippet(hit, color)
+            );
+        }
+
+        println!();
+    }
+
+    let total: usize = results.iter().map(Results::count).sum();
+    println!(
+        "{} in {}",
+        plural(total, "match", "matches"),
+        plural(results.len(), "note", "notes")
+    );
+}
+
+fn plural(count: usize, one: &str, many: &str) -> String {
+    format!("{count} {}", if count == 1 { one } else { many })
+}
+
+fn search_err(kind: clap::error::ErrorKind, msg: String) {
+    let cmd = clap::Command::new("search");
+
+    let err = clap::Error::raw(kind, format!("{msg}\n")).with_cmd(&cmd);
+
+    let _ = err.print();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `[` and `]` stand for the start and end of a highlight
+    fn snippet_of(text: &str, query: &str) -> String {
+        let pattern = pattern(query, SearchOptions { regex: true, case_sensitive: true, rank: RankBy::Matches }).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("note.md");
+        fs::write(&path, text).unwrap();
+
+        let hit = &search_file(&path, &pattern).unwrap()[0];
+        let highlight = AnsiColor::Red.on_default().bold();
+
+        snippet(hit, true)
+            .replace(&highlight.render().to_string(), "[")
+            .replace(&highlight.render_reset().to_string(), "]")
+    }
+
+    #[test]
+    fn matches_in_the_indentation_start_at_the_text() {
+        assert_eq!(snippet_of("    - item", r"^\s+-"), "[-] item");
+        assert_eq!(snippet_of("  - item", "  "), "- item");
+    }
+
+    #[test]
+    fn multibyte_chars_are_highlighted_whole() {
+        assert_eq!(snippet_of("  un café chaud", "é"), "un caf[é] chaud");
+    }
+
+    #[test]
+    fn matches_after_the_indentation_are_highlighted() {
+        assert_eq!(snippet_of("\t- one two", "two"), "- one [two]");
+    }
+
+    #[test]
+    fn long_lines_are_cut_around_the_match() {
+        let text = format!("{}match{}", "a".repeat(200), "b".repeat(200));
+        let snippet = snippet_of(&text, "match");
+
+        assert!(snippet.starts_with(&format!("…{}[match]", "a".repeat(SNIPPET_CONTEXT))));
+        assert!(snippet.ends_with("b…"));
+    }
+}